@@ -1,276 +1,255 @@
 use iced::{
-    executor,
-    widget::canvas,
-    widget::{
-        canvas::{Fill, Stroke, Style},
-        slider, Column, Row, Text,
-    },
-    Application, Color, Command, Length, Theme,
+    widget::canvas::{Fill, Frame, Stroke, Style},
+    Color, Point, Size,
 };
-use plotter::Plotter;
+use plotter::{
+    animation,
+    app::Key,
+    handle::Constraint,
+    hit::tooltip,
+    parameters,
+    params::{Preset, Spec, Value},
+    vec2d, Handle, Handles, HitMap, Plotter, Vector2D, Visualization,
+};
+use std::{cell::RefCell, f64::consts::TAU};
 
+#[derive(Default)]
 struct EllipseBillard {
-    state: State,
-}
-
-struct State {
-    cache: canvas::Cache,
-    eccentricity: f64,
-    angle: f64,
-    start_offset: f64,
-    reflection_count: usize,
+    /// Index and position of the bounces, filled each time the cache is redrawn
+    bounces: RefCell<HitMap<(usize, (f64, f64))>>,
+    /// Path followed by the ball, from the start point, filled each time the cache is redrawn
+    trajectory: RefCell<Vec<Vector2D>>,
 }
 
 const RESOLUTION: usize = 100;
 const DEFAULT_SCALE: f64 = 100.;
+/// Distance in pixels from a bounce under which it is hovered
+const HOVER_DISTANCE: f64 = 6.;
+/// Distance between the start point and the handle giving the direction of the ball
+const DIRECTION_LENGTH: f64 = 0.4;
+/// Speed of the animated ball, in world units per second
+const BALL_SPEED: f64 = 1.5;
+
+parameters! {
+    struct Params: Param |params| {
+        eccentricity: f64 = 0.8 => Spec::float("Eccentricity", 0.01..=0.99).step(0.01),
+        angle: f64 = std::f64::consts::FRAC_PI_4 => Spec::float("Angle", 0.0..=TAU)
+            .scale(1. / TAU)
+            .unit("τ")
+            .step(0.01),
+        start_offset: f64 = 0.3 => Spec::float("Starting offset", -0.99..=0.99).step(0.01),
+        reflection_count: usize = 50 => Spec::int("Reflection count", 10..=1000),
+    }
+}
+
+pub fn main() -> iced::Result {
+    plotter::run::<EllipseBillard>()
+}
 
-#[derive(Debug, Clone, Copy)]
-enum Message {
-    Eccentricity(f64),
-    Angle(f64),
-    StartOffset(f64),
-    ReflectionCount(usize),
+fn start(params: &Params) -> Vector2D {
+    let (a, _) = plotter::eccentricity_to_radius(params.eccentricity);
+    vec2d(params.start_offset * a, 0.)
 }
 
-impl Application for EllipseBillard {
-    type Executor = executor::Default;
-    type Message = Message;
-    type Theme = Theme;
-    type Flags = ();
+impl Visualization for EllipseBillard {
+    type Params = Params;
 
-    fn new(_: Self::Flags) -> (Self, Command<Self::Message>) {
-        (
-            Self {
-                state: State::new(),
-            },
-            Command::none(),
-        )
-    }
+    const TITLE: &'static str = "Billard in an Ellipse";
+    const ANIMATED: bool = true;
 
-    fn title(&self) -> String {
-        "Billard in an Ellipse".into()
+    fn plotter(&self, _params: &Params, size: Size) -> Plotter {
+        Plotter::new(RESOLUTION, size.width as _, size.height as _, DEFAULT_SCALE)
     }
 
-    fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
-        match message {
-            Message::Eccentricity(e) => self.state.eccentricity = e,
-            Message::Angle(th) => self.state.angle = th,
-            Message::StartOffset(s) => self.state.start_offset = s,
-            Message::ReflectionCount(r) => self.state.reflection_count = r,
-        }
-        self.state.cache.clear();
-
-        Command::none()
+    /// The start point slides on the major axis, the direction handle turns around it
+    fn handles(&self, params: &Params) -> Handles<Param> {
+        let (a, _) = plotter::eccentricity_to_radius(params.eccentricity);
+        let start = start(params);
+
+        Handles::new()
+            .with(
+                Handle::new(Param::StartOffset, start).constrained(Constraint::Segment(
+                    vec2d(-0.99 * a, 0.),
+                    vec2d(0.99 * a, 0.),
+                )),
+            )
+            .with(
+                Handle::new(
+                    Param::Angle,
+                    start + vec2d(params.angle.cos(), params.angle.sin()) * DIRECTION_LENGTH,
+                )
+                .constrained(Constraint::Circle {
+                    center: start,
+                    radius: DIRECTION_LENGTH,
+                }),
+            )
     }
 
-    fn view(&self) -> iced::Element<'_, Self::Message, iced::Renderer<Self::Theme>> {
-        Column::with_children(vec![
-            Row::with_children(vec![
-                Text::new(format!("Eccentricity ({:.2})", self.state.eccentricity)).into(),
-                slider(0.01..=0.99, self.state.eccentricity, |v| {
-                    Message::Eccentricity(v)
-                })
-                .step(0.01)
-                .into(),
-            ])
-            .into(),
-            Row::with_children(vec![
-                Text::new(format!(
-                    "Angle ({:3.2}tau)",
-                    self.state.angle / std::f64::consts::TAU
-                ))
-                .into(),
-                slider(0.0..=1., self.state.angle / std::f64::consts::TAU, |v| {
-                    Message::Angle(v * std::f64::consts::TAU)
-                })
-                .step(0.01)
-                .into(),
-            ])
-            .into(),
-            Row::with_children(vec![
-                Text::new(format!("Starting Offset ({:5.2})", self.state.start_offset)).into(),
-                slider(-0.99..=0.99, self.state.start_offset, |v| {
-                    Message::StartOffset(v)
-                })
-                .step(0.01)
-                .into(),
-            ])
-            .into(),
-            Row::with_children(vec![
-                Text::new(format!(
-                    "Reflection Count ({:4})",
-                    self.state.reflection_count
-                ))
-                .into(),
-                slider(10.0..=1000., self.state.reflection_count as f64, |v| {
-                    Message::ReflectionCount(v as usize)
-                })
-                .step(1.)
-                .into(),
-            ])
-            .into(),
-            canvas(&self.state)
-                .width(Length::Fill)
-                .height(Length::Fill)
-                .into(),
-        ])
-        .into()
+    ///
+    /// The regimes of the description, the foci being at an offset equal to the eccentricity:
+    /// starting between them the ball keeps crossing the segment joining them, starting outside
+    /// it never does.
+    ///
+    fn presets() -> Vec<Preset<Params>> {
+        let offset = |name, start_offset| {
+            Preset::new(
+                name,
+                Params {
+                    start_offset,
+                    ..Params::default()
+                },
+            )
+        };
+
+        vec![
+            offset("Between the foci", 0.3),
+            offset("Through a focus", 0.8),
+            offset("Outside the foci", 0.9),
+        ]
     }
-}
 
-impl State {
-    fn new() -> Self {
-        Self {
-            cache: Default::default(),
-            eccentricity: 0.8,
-            angle: std::f64::consts::FRAC_PI_4,
-            start_offset: 0.3,
-            reflection_count: 50,
+    fn drag(&self, params: &Params, key: Key<Self>, position: Vector2D) -> Option<Value> {
+        match key {
+            Param::StartOffset => {
+                let (a, _) = plotter::eccentricity_to_radius(params.eccentricity);
+                Some(Value::Float(position.x() / a))
+            }
+            Param::Angle => {
+                let direction = position - start(params);
+                let angle = direction.y().atan2(direction.x()).rem_euclid(TAU);
+                Some(Value::Float(angle))
+            }
+            _ => None,
         }
     }
-}
 
-impl<Message> canvas::Program<Message> for State {
-    type State = ();
+    fn draw(&self, params: &Params, frame: &mut Frame, plotter: &Plotter) {
+        let (a, b) = plotter::eccentricity_to_radius(params.eccentricity);
+
+        frame.stroke(
+            &plotter.centered_ellipse(a, b),
+            Stroke::default().with_width(2.0).with_color(Color::BLACK),
+        );
+
+        let intersections = |angle: f64, x0: f64, y0: f64| {
+            // We choose to either solve with x or y to try and avoid rounding errors
+            if angle.sin().abs() >= 0.5 {
+                let angle_factor = angle.cos() / angle.sin();
+                let offset = x0 - angle_factor * y0;
+
+                let alpha = a * a + (b * angle_factor).powi(2);
+                let beta = 2. * b * b * offset * angle_factor;
+                let gamma = b * b * (offset * offset - a * a);
+
+                // y coordinate of intersection between ray & ellipse is solution of
+                // alpha * x^2 + beta * x + gamma = 0
+                let delta = beta * beta - 4. * alpha * gamma;
+                assert!(
+                    delta >= 0.,
+                    "Delta should never be negative due to our choice"
+                );
+
+                let iy0 = (-beta + (delta).sqrt()) / (2. * alpha);
+                let iy1 = (-beta - (delta).sqrt()) / (2. * alpha);
+
+                (
+                    (iy0 * angle_factor + offset, iy0),
+                    (iy1 * angle_factor + offset, iy1),
+                )
+            } else {
+                let angle_factor = angle.tan();
+                let offset = y0 - angle_factor * x0;
+
+                let alpha = b * b + (a * angle_factor).powi(2);
+                let beta = 2. * a * a * offset * angle_factor;
+                let gamma = a * a * (offset * offset - b * b);
+
+                // x coordinate of intersection between ray & ellipse is solution of
+                // alpha * x^2 + beta * x + gamma = 0
+                let delta = beta * beta - 4. * alpha * gamma;
+                assert!(
+                    delta >= 0.,
+                    "Delta should never be negative due to our choice"
+                );
+
+                let ix0 = (-beta + (delta).sqrt()) / (2. * alpha);
+                let ix1 = (-beta - (delta).sqrt()) / (2. * alpha);
+
+                (
+                    (ix0, angle_factor * ix0 + offset),
+                    (ix1, angle_factor * ix1 + offset),
+                )
+            }
+        };
 
-    fn draw(
-        &self,
-        _state: &Self::State,
-        _theme: &Theme,
-        bounds: iced::Rectangle,
-        _cursor: canvas::Cursor,
-    ) -> Vec<canvas::Geometry> {
-        vec![self.cache.draw(bounds.size(), |frame| {
-            let iced::Size { width, height } = frame.size();
-            let width = width as _;
-            let height = height as _;
-            let plotter = Plotter::new(RESOLUTION, width, height, DEFAULT_SCALE);
-
-            let (a, b) = plotter::eccentricity_to_radius(self.eccentricity);
-
-            frame.stroke(
-                &plotter.centered_ellipse(a, b),
-                Stroke::default().with_width(2.0).with_color(Color::BLACK),
-            );
+        let angle = params.angle;
+        let (sx, sy) = (params.start_offset * a, 0.);
 
-            let intersections = |angle: f64, x0: f64, y0: f64| {
-                // We choose to either solve with x or y to try and avoid rounding errors
-                if angle.sin().abs() >= 0.5 {
-                    let angle_factor = angle.cos() / angle.sin();
-                    let offset = x0 - angle_factor * y0;
-
-                    let alpha = a * a + (b * angle_factor).powi(2);
-                    let beta = 2. * b * b * offset * angle_factor;
-                    let gamma = b * b * (offset * offset - a * a);
-
-                    // y coordinate of intersection between ray & ellipse is solution of
-                    // alpha * x^2 + beta * x + gamma = 0
-                    let delta = beta * beta - 4. * alpha * gamma;
-                    assert!(
-                        delta >= 0.,
-                        "Delta should never be negative due to our choice"
-                    );
-
-                    let iy0 = (-beta + (delta).sqrt()) / (2. * alpha);
-                    let iy1 = (-beta - (delta).sqrt()) / (2. * alpha);
-
-                    (
-                        (iy0 * angle_factor + offset, iy0),
-                        (iy1 * angle_factor + offset, iy1),
-                    )
-                } else {
-                    let angle_factor = angle.tan();
-                    let offset = y0 - angle_factor * x0;
-
-                    let alpha = b * b + (a * angle_factor).powi(2);
-                    let beta = 2. * a * a * offset * angle_factor;
-                    let gamma = a * a * (offset * offset - b * b);
-
-                    // x coordinate of intersection between ray & ellipse is solution of
-                    // alpha * x^2 + beta * x + gamma = 0
-                    let delta = beta * beta - 4. * alpha * gamma;
-                    assert!(
-                        delta >= 0.,
-                        "Delta should never be negative due to our choice"
-                    );
-
-                    let ix0 = (-beta + (delta).sqrt()) / (2. * alpha);
-                    let ix1 = (-beta - (delta).sqrt()) / (2. * alpha);
-
-                    (
-                        (ix0, angle_factor * ix0 + offset),
-                        (ix1, angle_factor * ix1 + offset),
-                    )
-                }
-            };
-
-            let angle = self.angle;
-            let (sx, sy) = (self.start_offset * a, 0.);
-
-            let ((ix0, iy0), (ix1, iy1)) = intersections(angle, sx, sy);
-
-            let (lower, upper) = if iy0 < 0. {
-                ((ix0, iy0), (ix1, iy1))
+        let ((ix0, iy0), (ix1, iy1)) = intersections(angle, sx, sy);
+
+        let (lower, upper) = if iy0 < 0. {
+            ((ix0, iy0), (ix1, iy1))
+        } else {
+            ((ix1, iy1), (ix0, iy0))
+        };
+
+        let first_intersection = if iy0 == 0. {
+            if angle == 0. {
+                (ix0.abs(), 0.)
             } else {
-                ((ix1, iy1), (ix0, iy0))
-            };
-
-            let first_intersection = if iy0 == 0. {
-                if angle == 0. {
-                    (ix0.abs(), 0.)
-                } else {
-                    (-ix0.abs(), 0.)
-                }
-            } else if angle > std::f64::consts::PI {
-                lower
+                (-ix0.abs(), 0.)
+            }
+        } else if angle > std::f64::consts::PI {
+            lower
+        } else {
+            upper
+        };
+
+        let tangent_vector = |x0: f64, y0: f64| {
+            if y0 == 0. {
+                (0., 1.)
+            } else if x0 == 0. {
+                (1., 0.)
             } else {
-                upper
-            };
-
-            let tangent_vector = |x0: f64, y0: f64| {
-                if y0 == 0. {
-                    (0., 1.)
-                } else if x0 == 0. {
-                    (1., 0.)
-                } else {
-                    (x0, y0 - (b * b / y0))
-                }
-            };
-
-            let normal_vector = |x0: f64, y0: f64| {
-                let (tx, ty) = tangent_vector(x0, y0);
-                let (nx, ny) = (-ty, tx);
-
-                let norm = (nx * nx + ny * ny).sqrt();
-
-                // We want the vector pointing to the center, it's the opposite to the one with
-                // the vector to (x0, y0) according to the dot product
-                if nx * x0 + ny * y0 >= 0. {
-                    (-nx / norm, -ny / norm)
-                } else {
-                    (nx / norm, ny / norm)
-                }
-            };
-
-            let outgoing_angle = |sx: f64, sy: f64, x0: f64, y0: f64| {
-                let (vx, vy) = (x0 - sx, y0 - sy);
-                let (nx, ny) = normal_vector(x0, y0);
-
-                let dot = vx * nx + vy * ny;
-
-                let (ox, oy) = (vx - 2. * dot * nx, vy - 2. * dot * ny);
-
-                oy.atan2(ox)
-            };
-
-            let out_th = outgoing_angle(sx, sy, first_intersection.0, first_intersection.1);
-
-            struct Ray {
-                start: (f64, f64),
-                angle: f64,
+                (x0, y0 - (b * b / y0))
             }
+        };
+
+        let normal_vector = |x0: f64, y0: f64| {
+            let (tx, ty) = tangent_vector(x0, y0);
+            let (nx, ny) = (-ty, tx);
 
-            let rays = plotter.path((0..self.reflection_count).scan(
+            let norm = (nx * nx + ny * ny).sqrt();
+
+            // We want the vector pointing to the center, it's the opposite to the one with
+            // the vector to (x0, y0) according to the dot product
+            if nx * x0 + ny * y0 >= 0. {
+                (-nx / norm, -ny / norm)
+            } else {
+                (nx / norm, ny / norm)
+            }
+        };
+
+        let outgoing_angle = |sx: f64, sy: f64, x0: f64, y0: f64| {
+            let (vx, vy) = (x0 - sx, y0 - sy);
+            let (nx, ny) = normal_vector(x0, y0);
+
+            let dot = vx * nx + vy * ny;
+
+            let (ox, oy) = (vx - 2. * dot * nx, vy - 2. * dot * ny);
+
+            oy.atan2(ox)
+        };
+
+        let out_th = outgoing_angle(sx, sy, first_intersection.0, first_intersection.1);
+
+        struct Ray {
+            start: (f64, f64),
+            angle: f64,
+        }
+
+        let bounces: Vec<_> = (0..params.reflection_count)
+            .scan(
                 Ray {
                     start: first_intersection,
                     angle: out_th,
@@ -290,59 +269,90 @@ impl<Message> canvas::Program<Message> for State {
 
                     Some((x0, y0))
                 },
-            ));
+            )
+            .collect();
 
-            frame.stroke(
-                &rays,
-                Stroke::default()
-                    .with_width(1.)
-                    .with_color(Color::new(1., 0., 0., 1.)),
-            );
+        let mut hits = self.bounces.borrow_mut();
+        hits.clear();
+        for (i, &bounce) in bounces.iter().enumerate() {
+            hits.point((i + 1, bounce), bounce);
+        }
 
-            frame.stroke(
-                &plotter.path([(sx, sy), first_intersection]),
-                Stroke::default()
-                    .with_width(2.)
-                    .with_color(Color::new(0., 0., 1., 1.)),
-            );
+        let mut trajectory = self.trajectory.borrow_mut();
+        trajectory.clear();
+        trajectory.push(vec2d(sx, sy));
+        trajectory.extend(bounces.iter().copied().map(Vector2D::from));
+
+        let rays = plotter.path(bounces);
+
+        frame.stroke(
+            &rays,
+            Stroke::default()
+                .with_width(1.)
+                .with_color(Color::new(1., 0., 0., 1.)),
+        );
+
+        frame.stroke(
+            &plotter.path([(sx, sy), first_intersection]),
+            Stroke::default()
+                .with_width(2.)
+                .with_color(Color::new(0., 0., 1., 1.)),
+        );
+
+        frame.stroke(
+            &plotter.angle_marker((sx, sy), (1., 0.), (angle.cos(), angle.sin()), 0.2),
+            Stroke::default()
+                .with_width(1.)
+                .with_color(Color::new(0., 0., 1., 1.)),
+        );
+
+        frame.fill(
+            &plotter.circle(-a * params.eccentricity, 0., 0.05),
+            Fill {
+                style: Style::Solid(Color::new(0.3, 0.21, 0.82, 1.)),
+                ..Default::default()
+            },
+        );
+        frame.fill(
+            &plotter.circle(a * params.eccentricity, 0., 0.05),
+            Fill {
+                style: Style::Solid(Color::new(0.3, 0.21, 0.82, 1.)),
+                ..Default::default()
+            },
+        );
+    }
 
+    fn animate(&self, _params: &Params, frame: &mut Frame, plotter: &Plotter, time: f64) {
+        let trajectory = self.trajectory.borrow();
+        let distance = (time * BALL_SPEED) % animation::length(&trajectory).max(f64::EPSILON);
+
+        if let Some(position) = animation::point_along(&trajectory, distance) {
             frame.fill(
-                &plotter.circle(-a * self.eccentricity, 0., 0.05),
-                Fill {
-                    style: Style::Solid(Color::new(0.3, 0.21, 0.82, 1.)),
-                    ..Default::default()
-                },
-            );
-            frame.fill(
-                &plotter.circle(a * self.eccentricity, 0., 0.05),
+                &plotter.circle(position.x(), position.y(), 0.04),
                 Fill {
-                    style: Style::Solid(Color::new(0.3, 0.21, 0.82, 1.)),
+                    style: Style::Solid(Color::new(0.1, 0.5, 0.1, 1.)),
                     ..Default::default()
                 },
             );
-        })]
+        }
     }
-}
-
-pub fn main() -> iced::Result {
-    #[cfg(target_arch = "wasm32")]
-    let platform_specific = {
-        std::panic::set_hook(Box::new(console_error_panic_hook::hook));
 
-        iced::window::PlatformSpecific {
-            target: Some("iced_root".into()),
+    fn overlay(
+        &self,
+        _params: &Params,
+        frame: &mut Frame,
+        plotter: &Plotter,
+        cursor: Option<Point>,
+    ) {
+        if let Some(position) = cursor {
+            if let Some(hit) = self
+                .bounces
+                .borrow()
+                .nearest(plotter, position, HOVER_DISTANCE)
+            {
+                let &(i, (x, y)) = hit.id;
+                tooltip(frame, position, format!("Bounce {i} at ({x:.3}, {y:.3})"));
+            }
         }
-    };
-
-    #[cfg(not(target_arch = "wasm32"))]
-    let platform_specific = iced::window::PlatformSpecific::default();
-
-    EllipseBillard::run(iced::Settings {
-        antialiasing: true,
-        window: iced::window::Settings {
-            platform_specific,
-            ..Default::default()
-        },
-        ..Default::default()
-    })
+    }
 }
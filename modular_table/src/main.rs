@@ -1,280 +1,222 @@
-use std::collections::HashMap;
+use std::{cell::RefCell, collections::HashMap};
 
 use iced::{
-    executor,
-    widget::canvas,
-    widget::{
-        canvas::{Stroke, Style},
-        checkbox, slider, Column, Row, Text,
-    },
-    Application, Color, Command, Length, Theme,
+    widget::canvas::{Fill, Frame, Stroke, Style},
+    Color, Point, Size,
 };
 use palette::{rgb::Rgb, FromColor, Lch, Srgb};
 use petgraph::graph::Graph;
-use plotter::Plotter;
+use plotter::{
+    app::Key,
+    hit::tooltip,
+    parameters,
+    params::{Preset, Spec},
+    sweep::Sweeper,
+    HitMap, Parameters, Plotter, Visualization,
+};
 
+#[derive(Default)]
 struct ModularTable {
-    state: State,
-}
-
-struct State {
-    cache: canvas::Cache,
-    modulo: u64,
-    scale: f64,
-    multiplier: u64,
-    colored: bool,
-    label: bool,
-    arrow: bool,
+    /// Filled with the nodes each time the cache is redrawn
+    nodes: RefCell<HitMap<u64>>,
 }
 
 const RESOLUTION: usize = 100;
 const DEFAULT_SCALE: f64 = 180.;
-
-#[derive(Debug, Clone, Copy)]
-enum Message {
-    Modulo(u64),
-    Scale(f64),
-    Multiplier(u64),
-    Colored(bool),
-    Labeled(bool),
-    Arrow(bool),
+/// Distance in pixels from a node under which it is hovered
+const HOVER_DISTANCE: f64 = 8.;
+/// Multipliers giving nice patterns at large moduli, see the description. The sweep skips the
+/// ones that are not below the modulo.
+const INTERESTING_MULTIPLIERS: &[f64] = &[
+    2., 3., 4., 5., 6., 7., 8., 9., 10., 11., 12., 13., 14., 15., 21., 44., 59., 61.,
+];
+
+parameters! {
+    struct Params: Param |params| {
+        modulo: u64 = 10 => Spec::int("Modulo", 3..=300),
+        multiplier: u64 = 2 => Spec::int("Multiplier", 2..=params.modulo as i64 - 1),
+        scale: f64 = 1. => Spec::float("Scale", 1.0..=6.).step(0.1),
+        colored: bool = false => Spec::toggle("Colors for components"),
+        label: bool = true => Spec::toggle("Labels on the nodes"),
+        arrow: bool = false => Spec::toggle("Arrows"),
+    }
 }
 
 pub fn main() -> iced::Result {
-    #[cfg(target_arch = "wasm32")]
-    let platform_specific = {
-        std::panic::set_hook(Box::new(console_error_panic_hook::hook));
-
-        iced::window::PlatformSpecific {
-            target: Some("iced_root".into()),
-        }
-    };
-
-    #[cfg(not(target_arch = "wasm32"))]
-    let platform_specific = iced::window::PlatformSpecific::default();
-
-    ModularTable::run(iced::Settings {
-        antialiasing: true,
-        window: iced::window::Settings {
-            platform_specific,
-            ..Default::default()
-        },
-        ..Default::default()
-    })
+    plotter::run::<ModularTable>()
 }
 
-impl Application for ModularTable {
-    type Executor = executor::Default;
-    type Message = Message;
-    type Theme = Theme;
-    type Flags = ();
-
-    fn new(_: Self::Flags) -> (Self, Command<Self::Message>) {
-        (
-            Self {
-                state: State::new(),
-            },
-            Command::none(),
-        )
+fn image(params: &Params, i: u64) -> u64 {
+    let r = (i * params.multiplier) % params.modulo;
+    if r == 0 {
+        params.modulo
+    } else {
+        r
     }
+}
 
-    fn title(&self) -> String {
-        "Modular Table".into()
-    }
+impl Visualization for ModularTable {
+    type Params = Params;
 
-    fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
-        match message {
-            Message::Modulo(n) => {
-                self.state.modulo = n;
-                if self.state.multiplier >= n {
-                    self.state.multiplier = n - 1;
-                }
-            }
-            Message::Scale(s) => self.state.scale = s,
-            Message::Multiplier(m) => self.state.multiplier = m,
-            Message::Colored(b) => self.state.colored = b,
-            Message::Labeled(l) => self.state.label = l,
-            Message::Arrow(a) => self.state.arrow = a,
-        }
-        self.state.cache.clear();
+    const TITLE: &'static str = "Modular Table";
 
-        Command::none()
+    fn plotter(&self, params: &Params, size: Size) -> Plotter {
+        Plotter::new(
+            RESOLUTION,
+            size.width as _,
+            size.height as _,
+            params.scale * DEFAULT_SCALE,
+        )
     }
 
-    fn view(&self) -> iced::Element<'_, Self::Message, iced::Renderer<Self::Theme>> {
-        Column::with_children(vec![
-            Row::with_children(vec![
-                Text::new(format!("Modulo ({:4})", self.state.modulo)).into(),
-                slider(3.0..=300.0, self.state.modulo as _, |v| {
-                    Message::Modulo(v as _)
-                })
-                .into(),
-            ])
-            .into(),
-            Row::with_children(vec![
-                Text::new(format!("Multiplier ({:4})", self.state.multiplier)).into(),
-                slider(
-                    2.0..=((self.state.modulo - 1) as _),
-                    self.state.multiplier as _,
-                    |v| Message::Multiplier(v as _),
-                )
-                .into(),
-            ])
-            .into(),
-            Row::with_children(vec![
-                Text::new(format!("Scale ({:4.2})", self.state.scale)).into(),
-                slider(1.0..=6., self.state.scale, Message::Scale)
-                    .step(0.1)
-                    .into(),
-            ])
-            .into(),
-            Row::with_children(vec![
-                checkbox(
-                    "Colors for components",
-                    self.state.colored,
-                    Message::Colored,
-                )
-                .into(),
-                checkbox("Labels on the nodes", self.state.label, Message::Labeled).into(),
-                checkbox("Arrows", self.state.arrow, Message::Arrow).into(),
-            ])
-            .into(),
-            canvas(&self.state)
-                .width(Length::Fill)
-                .height(Length::Fill)
-                .into(),
-        ])
-        .padding(5)
-        .into()
+    fn sweeper(params: &Params) -> Sweeper<Key<Self>> {
+        Sweeper::new(params.numeric()).with_values(Param::Multiplier, INTERESTING_MULTIPLIERS)
     }
-}
 
-impl State {
-    fn new() -> Self {
-        Self {
-            cache: canvas::Cache::new(),
-            modulo: 10,
-            multiplier: 2,
-            scale: 1.,
-            colored: false,
-            label: true,
-            arrow: false,
-        }
+    /// The patterns of the description, at a large modulo where the labels would be too dense
+    fn presets() -> Vec<Preset<Params>> {
+        let large = |name, multiplier| {
+            Preset::new(
+                name,
+                Params {
+                    modulo: 300,
+                    multiplier,
+                    label: false,
+                    ..Params::default()
+                },
+            )
+        };
+
+        vec![
+            large("Cardioid", 2),
+            large("Nephroid", 3),
+            large("Multiplier 21", 21),
+            large("Multiplier 44", 44),
+            large("Multiplier 59", 59),
+            large("Multiplier 61", 61),
+        ]
     }
-}
-
-impl<Message> canvas::Program<Message> for State {
-    type State = ();
-
-    fn draw(
-        &self,
-        _state: &Self::State,
-        _theme: &Theme,
-        bounds: iced::Rectangle,
-        _cursor: canvas::Cursor,
-    ) -> Vec<canvas::Geometry> {
-        vec![self.cache.draw(bounds.size(), |frame| {
-            let iced::Size { width, height } = frame.size();
-            let plotter = Plotter::new(
-                RESOLUTION,
-                width as _,
-                height as _,
-                self.scale * DEFAULT_SCALE,
-            );
 
-            frame.stroke(
-                &plotter.centered_circle(1.),
-                Stroke::default().with_width(3.0).with_color(Color::BLACK),
+    fn draw(&self, params: &Params, frame: &mut Frame, plotter: &Plotter) {
+        let mut hits = self.nodes.borrow_mut();
+        hits.clear();
+
+        frame.stroke(
+            &plotter.centered_circle(1.),
+            Stroke::default().with_width(3.0).with_color(Color::BLACK),
+        );
+
+        let step = std::f64::consts::TAU / params.modulo as f64;
+        let coord = |i| {
+            let angle = std::f64::consts::FRAC_PI_2 - (i as f64 * step);
+            let x = angle.cos();
+            let y = angle.sin();
+
+            (x, y)
+        };
+
+        let mut graph = Graph::<u64, (), _>::new_undirected();
+        let mut nodes = HashMap::new();
+        let mut graph_directed = Graph::<u64, (), _>::new();
+        let mut nodes_directed = HashMap::new();
+
+        for i in 1..=params.modulo {
+            let (x, y) = coord(i);
+
+            let r = image(params, i);
+
+            let &mut i_idx = nodes.entry(i).or_insert_with(|| graph.add_node(i));
+            let &mut r_idx = nodes.entry(r).or_insert_with(|| graph.add_node(r));
+            graph.add_edge(i_idx, r_idx, ());
+
+            let &mut i_idx = nodes_directed
+                .entry(i)
+                .or_insert_with(|| graph_directed.add_node(i));
+            let &mut r_idx = nodes_directed
+                .entry(r)
+                .or_insert_with(|| graph_directed.add_node(r));
+            graph_directed.add_edge(i_idx, r_idx, ());
+
+            frame.fill(
+                &plotter.circle(x, y, 0.03),
+                Fill {
+                    style: Style::Solid(Color::BLACK),
+                    ..Default::default()
+                },
             );
+            hits.disk(i, (x, y), 0.03);
 
-            let step = std::f64::consts::TAU / self.modulo as f64;
-            let coord = |i| {
-                let angle = std::f64::consts::FRAC_PI_2 - (i as f64 * step);
-                let x = angle.cos();
-                let y = angle.sin();
-
-                (x, y)
-            };
-
-            let mut graph = Graph::<u64, (), _>::new_undirected();
-            let mut nodes = HashMap::new();
-            let mut graph_directed = Graph::<u64, (), _>::new();
-            let mut nodes_directed = HashMap::new();
-
-            for i in 1..=self.modulo {
-                let (x, y) = coord(i);
-
-                let r = (i * self.multiplier) % self.modulo;
-                let r = if r == 0 { self.modulo } else { r };
-
-                let &mut i_idx = nodes.entry(i).or_insert_with(|| graph.add_node(i));
-                let &mut r_idx = nodes.entry(r).or_insert_with(|| graph.add_node(r));
-                graph.add_edge(i_idx, r_idx, ());
-
-                let &mut i_idx = nodes_directed
-                    .entry(i)
-                    .or_insert_with(|| graph_directed.add_node(i));
-                let &mut r_idx = nodes_directed
-                    .entry(r)
-                    .or_insert_with(|| graph_directed.add_node(r));
-                graph_directed.add_edge(i_idx, r_idx, ());
-
-                frame.fill(
-                    &plotter.circle(x, y, 0.03),
-                    canvas::Fill {
-                        style: Style::Solid(Color::BLACK),
-                        ..Default::default()
-                    },
-                );
-
-                if self.label {
-                    frame.fill_text(plotter.text(x * 1.1, y * 1.1, i.to_string()));
-                }
+            if params.label {
+                frame.fill_text(plotter.text(x * 1.1, y * 1.1, i.to_string()));
             }
+        }
 
-            let components = petgraph::algo::kosaraju_scc(&graph);
-            let cn = components.len() as f64;
+        let components = petgraph::algo::kosaraju_scc(&graph);
+        let cn = components.len() as f64;
 
-            for (i, component) in components.iter().enumerate() {
-                let lch = Lch::new(80., 100., (i as f64 / cn) * 360.);
-                let Rgb {
-                    red, green, blue, ..
-                } = Srgb::from_color(lch);
+        for (i, component) in components.iter().enumerate() {
+            let lch = Lch::new(80., 100., (i as f64 / cn) * 360.);
+            let Rgb {
+                red, green, blue, ..
+            } = Srgb::from_color(lch);
 
-                let stroke = if self.colored {
-                    Stroke::default().with_color(Color::from_rgb(
-                        red as f32,
-                        green as f32,
-                        blue as f32,
-                    ))
-                } else {
-                    Stroke::default().with_color(Color::from_rgb(1.0, 0.0, 0.0))
-                };
+            let stroke = if params.colored {
+                Stroke::default().with_color(Color::from_rgb(red as f32, green as f32, blue as f32))
+            } else {
+                Stroke::default().with_color(Color::from_rgb(1.0, 0.0, 0.0))
+            };
 
-                for (i, &a) in component.iter().take(component.len() - 1).enumerate() {
-                    for &b in &component[i + 1..] {
-                        if graph.contains_edge(a, b) {
-                            let &a = graph.node_weight(a).unwrap();
-                            let &b = graph.node_weight(b).unwrap();
-                            if self.arrow {
-                                let ia = nodes_directed[&a];
-                                let ib = nodes_directed[&b];
-                                let (start, end) = if graph_directed.contains_edge(ia, ib) {
-                                    (a, b)
-                                } else {
-                                    (b, a)
-                                };
-                                frame.stroke(
-                                    &plotter.arrow_absolute_size(coord(start), coord(end), 0.05),
-                                    stroke.clone(),
-                                );
+            for (i, &a) in component.iter().take(component.len() - 1).enumerate() {
+                for &b in &component[i + 1..] {
+                    if graph.contains_edge(a, b) {
+                        let &a = graph.node_weight(a).unwrap();
+                        let &b = graph.node_weight(b).unwrap();
+                        if params.arrow {
+                            let ia = nodes_directed[&a];
+                            let ib = nodes_directed[&b];
+                            let (start, end) = if graph_directed.contains_edge(ia, ib) {
+                                (a, b)
                             } else {
-                                frame.stroke(&plotter.path([coord(a), coord(b)]), stroke.clone());
-                            }
+                                (b, a)
+                            };
+                            frame.stroke(
+                                &plotter.arrow_absolute_size(coord(start), coord(end), 0.05),
+                                stroke.clone(),
+                            );
+                        } else {
+                            frame.stroke(&plotter.path([coord(a), coord(b)]), stroke.clone());
                         }
                     }
                 }
             }
-        })]
+        }
+    }
+
+    fn overlay(
+        &self,
+        params: &Params,
+        frame: &mut Frame,
+        plotter: &Plotter,
+        cursor: Option<Point>,
+    ) {
+        if let Some(position) = cursor {
+            if let Some(hit) = self
+                .nodes
+                .borrow()
+                .nearest(plotter, position, HOVER_DISTANCE)
+            {
+                let i = *hit.id;
+                tooltip(
+                    frame,
+                    position,
+                    format!(
+                        "{i} × {} = {} (mod {})",
+                        params.multiplier,
+                        image(params, i),
+                        params.modulo
+                    ),
+                );
+            }
+        }
     }
 }
@@ -0,0 +1,360 @@
+//! Time based animations.
+//!
+//! A [`Clock`] gives the animation time, it is advanced by the frames of the window while it
+//! plays. The static parts of a drawing stay in their `canvas::Cache`, only the animated ones
+//! are drawn again each frame with [`layer`].
+
+use crate::Vector2D;
+use iced_graphics::{
+    widget::canvas::{Frame, Geometry},
+    Size,
+};
+use iced_native::{
+    subscription::Subscription,
+    time::Instant,
+    widget::{button, slider, text},
+    window, Element,
+};
+
+/// Duration of one step when the clock is paused, in seconds
+const STEP: f64 = 1. / 60.;
+
+#[derive(Debug, Clone, Copy)]
+pub enum ClockMessage {
+    Tick(Instant),
+    Toggle,
+    Step,
+    Reset,
+    Speed(f64),
+}
+
+pub struct Clock {
+    time: f64,
+    speed: f64,
+    playing: bool,
+    last_tick: Option<Instant>,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self {
+            time: 0.,
+            speed: 1.,
+            playing: true,
+            last_tick: None,
+        }
+    }
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn paused() -> Self {
+        Self {
+            playing: false,
+            ..Self::default()
+        }
+    }
+
+    /// Animation time in seconds
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Jumps to `time`, used when the frames are not drawn in real time
+    pub fn set_time(&mut self, time: f64) {
+        self.time = time;
+        self.last_tick = None;
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn update(&mut self, message: ClockMessage) {
+        match message {
+            // A frame requested before pausing can still arrive
+            ClockMessage::Tick(_) if !self.playing => {}
+            ClockMessage::Tick(now) => {
+                if let Some(last) = self.last_tick {
+                    self.time += now.duration_since(last).as_secs_f64() * self.speed;
+                }
+                self.last_tick = Some(now);
+            }
+            ClockMessage::Toggle => {
+                self.playing = !self.playing;
+                // The time spent paused must not be counted on the next tick
+                self.last_tick = None;
+            }
+            ClockMessage::Step => self.time += STEP * self.speed,
+            ClockMessage::Reset => self.time = 0.,
+            ClockMessage::Speed(s) => self.speed = s,
+        }
+    }
+
+    /// One tick per frame while playing
+    pub fn subscription(&self) -> Subscription<ClockMessage> {
+        if self.playing {
+            window::frames().map(ClockMessage::Tick)
+        } else {
+            Subscription::none()
+        }
+    }
+
+    /// Play/pause, step, reset and speed controls
+    pub fn controls<'a, Message, Renderer>(
+        &self,
+        on_message: fn(ClockMessage) -> Message,
+    ) -> Element<'a, Message, Renderer>
+    where
+        Message: Clone + 'a,
+        Renderer: iced_native::text::Renderer + 'a,
+        Renderer::Theme: button::StyleSheet + slider::StyleSheet + text::StyleSheet,
+    {
+        let label = if self.playing { "Pause" } else { "Play" };
+
+        let mut step = button(text("Step"));
+        if !self.playing {
+            step = step.on_press(on_message(ClockMessage::Step));
+        }
+
+        iced_native::widget::row(vec![
+            button(text(label))
+                .on_press(on_message(ClockMessage::Toggle))
+                .into(),
+            step.into(),
+            button(text("Reset"))
+                .on_press(on_message(ClockMessage::Reset))
+                .into(),
+            text(format!("Speed (x{:.2})", self.speed)).into(),
+            slider(0.1..=4.0, self.speed, move |s| {
+                on_message(ClockMessage::Speed(s))
+            })
+            .step(0.05)
+            .into(),
+        ])
+        .spacing(5)
+        .into()
+    }
+}
+
+/// Draws a layer that is not cached, to be redrawn at each frame
+pub fn layer<F>(size: Size, time: f64, draw: F) -> Geometry
+where
+    F: FnOnce(&mut Frame, f64),
+{
+    let mut frame = Frame::new(size);
+    draw(&mut frame, time);
+    frame.into_geometry()
+}
+
+/// Easing functions, mapping `[0, 1]` to `[0, 1]`
+pub mod ease {
+    use std::f64::consts::PI;
+
+    pub fn linear(t: f64) -> f64 {
+        t
+    }
+
+    pub fn in_quad(t: f64) -> f64 {
+        t * t
+    }
+
+    pub fn out_quad(t: f64) -> f64 {
+        1. - in_quad(1. - t)
+    }
+
+    pub fn in_out_quad(t: f64) -> f64 {
+        if t < 0.5 {
+            2. * t * t
+        } else {
+            1. - 2. * (1. - t) * (1. - t)
+        }
+    }
+
+    pub fn in_cubic(t: f64) -> f64 {
+        t * t * t
+    }
+
+    pub fn out_cubic(t: f64) -> f64 {
+        1. - in_cubic(1. - t)
+    }
+
+    pub fn in_out_cubic(t: f64) -> f64 {
+        if t < 0.5 {
+            4. * t * t * t
+        } else {
+            1. - 4. * (1. - t).powi(3)
+        }
+    }
+
+    pub fn in_out_sine(t: f64) -> f64 {
+        (1. - (PI * t).cos()) / 2.
+    }
+
+    pub fn smoothstep(t: f64) -> f64 {
+        t * t * (3. - 2. * t)
+    }
+
+    /// Like a ball dropped on the floor
+    pub fn out_bounce(t: f64) -> f64 {
+        const N: f64 = 7.5625;
+        const D: f64 = 2.75;
+
+        if t < 1. / D {
+            N * t * t
+        } else if t < 2. / D {
+            let t = t - 1.5 / D;
+            N * t * t + 0.75
+        } else if t < 2.5 / D {
+            let t = t - 2.25 / D;
+            N * t * t + 0.9375
+        } else {
+            let t = t - 2.625 / D;
+            N * t * t + 0.984375
+        }
+    }
+}
+
+/// Position in `[0, 1]` in a cycle of length `period`, starting over at each cycle
+pub fn cycle(time: f64, period: f64) -> f64 {
+    (time / period).rem_euclid(1.)
+}
+
+/// Position in `[0, 1]` in a cycle of length `period`, going back and forth
+pub fn ping_pong(time: f64, period: f64) -> f64 {
+    1. - (2. * cycle(time, period) - 1.).abs()
+}
+
+pub fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+/// The point at `distance` along the polyline, clamped to its ends
+pub fn point_along(points: &[Vector2D], distance: f64) -> Option<Vector2D> {
+    let mut remaining = distance.max(0.);
+    for w in points.windows(2) {
+        let length = (w[1] - w[0]).norm();
+        if remaining <= length {
+            return Some(w[0] + (w[1] - w[0]) * (remaining / length.max(f64::EPSILON)));
+        }
+        remaining -= length;
+    }
+
+    points.last().copied()
+}
+
+/// Total length of a polyline
+pub fn length(points: &[Vector2D]) -> f64 {
+    points.windows(2).map(|w| (w[1] - w[0]).norm()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec2d;
+    use std::time::Duration;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{a} != {b}");
+    }
+
+    #[test]
+    fn cycles_at_period_boundaries() {
+        assert_close(cycle(0., 2.), 0.);
+        assert_close(cycle(1.5, 2.), 0.75);
+        assert_close(cycle(2., 2.), 0.);
+        assert_close(cycle(5., 2.), 0.5);
+        // Before the start, the cycle is still in [0, 1)
+        assert_close(cycle(-0.5, 2.), 0.75);
+
+        assert_close(ping_pong(0., 2.), 0.);
+        assert_close(ping_pong(1., 2.), 1.);
+        assert_close(ping_pong(1.5, 2.), 0.5);
+        assert_close(ping_pong(2., 2.), 0.);
+        assert_close(ping_pong(2.5, 2.), 0.5);
+        // Reflected, so the same on both sides of the turn
+        assert_close(ping_pong(0.8, 2.), ping_pong(1.2, 2.));
+    }
+
+    #[test]
+    fn points_along_a_polyline() {
+        let points = [vec2d(0., 0.), vec2d(3., 0.), vec2d(3., 4.)];
+        assert_close(length(&points), 7.);
+
+        let along = |d| point_along(&points, d).unwrap();
+        assert_eq!(along(-1.), vec2d(0., 0.));
+        assert_eq!(along(1.5), vec2d(1.5, 0.));
+        assert_eq!(along(3.), vec2d(3., 0.));
+        assert_eq!(along(5.), vec2d(3., 2.));
+        assert_eq!(along(100.), vec2d(3., 4.));
+
+        assert_eq!(point_along(&[], 1.), None);
+        assert_eq!(point_along(&[vec2d(1., 2.)], 1.), Some(vec2d(1., 2.)));
+        // Repeated points do not divide by zero
+        let repeated = [vec2d(1., 1.), vec2d(1., 1.), vec2d(2., 1.)];
+        assert_eq!(point_along(&repeated, 0.5), Some(vec2d(1.5, 1.)));
+    }
+
+    #[test]
+    fn easings_keep_their_ends() {
+        let easings: [fn(f64) -> f64; 10] = [
+            ease::linear,
+            ease::in_quad,
+            ease::out_quad,
+            ease::in_out_quad,
+            ease::in_cubic,
+            ease::out_cubic,
+            ease::in_out_cubic,
+            ease::in_out_sine,
+            ease::smoothstep,
+            ease::out_bounce,
+        ];
+        for ease in easings {
+            assert_close(ease(0.), 0.);
+            assert_close(ease(1.), 1.);
+        }
+        assert_close(ease::in_out_quad(0.5), 0.5);
+        assert_close(ease::in_out_cubic(0.5), 0.5);
+        assert_close(lerp(2., 4., 0.25), 2.5);
+    }
+
+    #[test]
+    fn clock_speed_and_pause() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        let mut clock = Clock::new();
+        // The first tick only starts counting
+        clock.update(ClockMessage::Tick(at(0)));
+        clock.update(ClockMessage::Tick(at(500)));
+        assert_close(clock.time(), 0.5);
+
+        clock.update(ClockMessage::Speed(2.));
+        clock.update(ClockMessage::Tick(at(1000)));
+        assert_close(clock.time(), 1.5);
+
+        // Paused, the time neither follows the frames nor counts the pause once playing again
+        clock.update(ClockMessage::Toggle);
+        assert!(!clock.is_playing());
+        clock.update(ClockMessage::Tick(at(2000)));
+        assert_close(clock.time(), 1.5);
+        clock.update(ClockMessage::Step);
+        assert_close(clock.time(), 1.5 + 2. * STEP);
+
+        clock.update(ClockMessage::Toggle);
+        clock.update(ClockMessage::Tick(at(5000)));
+        clock.update(ClockMessage::Tick(at(5250)));
+        assert_close(clock.time(), 2. + 2. * STEP);
+
+        clock.update(ClockMessage::Reset);
+        assert_close(clock.time(), 0.);
+        assert_close(clock.speed(), 2.);
+    }
+}
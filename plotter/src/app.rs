@@ -0,0 +1,601 @@
+//! Shared application harness for the posts.
+//!
+//! A post implements [`Visualization`]: its parameters and how to draw them, and optionally
+//! handles, clicks and drags on the canvas, an overlay, an animation or presets. [`run`]
+//! supplies the rest: the window or the web canvas, the parameter panel, the sweeps, the
+//! animation clock, the cache of the drawing, the command line, and the links to the current
+//! parameters.
+//!
+//! On the web, the parameters are kept in the query of the page so that its address can be
+//! shared. Natively, they are given on the [command line](crate::cli), which can also draw
+//! them to a file without opening a window. Otherwise, the app starts from the parameters
+//! [saved](crate::persist) when it was last used.
+
+use crate::{
+    animation::{self, ClockMessage},
+    handle::{Drag, HandleState},
+    params::{self, Preset, Value},
+    persist::Store,
+    sweep::{SweepMessage, Sweeper},
+    Clock, Handles, Parameters, Plotter, Vector2D,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    cli::{Cli, Mode},
+    raster,
+    record::{self, RecordOptions},
+    svg,
+};
+use iced::{
+    executor, mouse,
+    time::Instant,
+    touch,
+    widget::{
+        button,
+        canvas::{
+            self,
+            event::{self, Event},
+            Cursor, Frame, Geometry,
+        },
+        text, Column, Row,
+    },
+    window, Application, Command, Length, Point, Rectangle, Size, Subscription, Theme,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+use std::time::Duration;
+
+const RESOLUTION: usize = 100;
+const DEFAULT_SCALE: f64 = 100.;
+/// The parameters are saved once they stay the same for this long, not during drags and sweeps
+const SAVE_DELAY: Duration = Duration::from_millis(500);
+
+/// Key of the parameters of a visualization
+pub type Key<V> = <<V as Visualization>::Params as Parameters>::Key;
+
+pub trait Visualization: Default + 'static {
+    type Params: Parameters + Default + Clone + PartialEq;
+
+    const TITLE: &'static str;
+
+    /// Shows the clock controls and draws [`animate`](Visualization::animate) at each frame
+    const ANIMATED: bool = false;
+
+    /// Draws what only depends on the parameters, it is cached until one of them changes
+    fn draw(&self, params: &Self::Params, frame: &mut Frame, plotter: &Plotter);
+
+    fn plotter(&self, _params: &Self::Params, size: Size) -> Plotter {
+        Plotter::new(RESOLUTION, size.width as _, size.height as _, DEFAULT_SCALE)
+    }
+
+    ///
+    /// Updates what is derived from the parameters after `changed` was set. It is called with
+    /// `None` for the initial parameters.
+    ///
+    fn update(&mut self, _params: &Self::Params, _changed: Option<Key<Self>>) {}
+
+    /// Handles that change the parameter they are identified by when dragged
+    fn handles(&self, _params: &Self::Params) -> Handles<Key<Self>> {
+        Handles::new()
+    }
+
+    /// Value of the parameter of a handle dragged to `position`
+    fn drag(&self, _params: &Self::Params, _key: Key<Self>, _position: Vector2D) -> Option<Value> {
+        None
+    }
+
+    /// Parameters set by a click on the canvas at `position`, away from the handles
+    fn click(&self, _params: &Self::Params, _position: Vector2D) -> Vec<(Key<Self>, Value)> {
+        Vec::new()
+    }
+
+    ///
+    /// Parameters set by dragging the canvas from `from` to `to`, when the drag started away from
+    /// the handles and the click set nothing. It is called at each movement, e.g. to rotate a
+    /// view.
+    ///
+    fn drag_canvas(
+        &self,
+        _params: &Self::Params,
+        _plotter: &Plotter,
+        _from: Vector2D,
+        _to: Vector2D,
+    ) -> Vec<(Key<Self>, Value)> {
+        Vec::new()
+    }
+
+    ///
+    /// Drawn at each frame above the rest, like hover tooltips. There is no cursor while a handle
+    /// is hovered or dragged.
+    ///
+    fn overlay(
+        &self,
+        _params: &Self::Params,
+        _frame: &mut Frame,
+        _plotter: &Plotter,
+        _cursor: Option<Point>,
+    ) {
+    }
+
+    /// Draws the animated layer at `time` seconds, see [`ANIMATED`](Visualization::ANIMATED)
+    fn animate(&self, _params: &Self::Params, _frame: &mut Frame, _plotter: &Plotter, _time: f64) {}
+
+    fn sweeper(params: &Self::Params) -> Sweeper<Key<Self>> {
+        Sweeper::new(params.numeric())
+    }
+
+    ///
+    /// Version of the parameters, to bump when the saved ones would no longer make sense, e.g.
+    /// when a parameter changes units
+    ///
+    const VERSION: u32 = 1;
+
+    /// The interesting configurations, to jump to them from a list
+    fn presets() -> Vec<Preset<Self::Params>> {
+        Vec::new()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message<K> {
+    Set(K, Value),
+    /// Sets the parameters in order, e.g. the coordinates of a clicked point
+    SetAll(Vec<(K, Value)>),
+    Sweep(SweepMessage<K>),
+    Clock(ClockMessage),
+    /// Loads the preset at this index
+    Preset(usize),
+    Reset,
+    /// A frame while there are unsaved changes, they are saved once they settle
+    Save(Instant),
+}
+
+struct App<V: Visualization> {
+    canvas: Visualized<V>,
+    sweeper: Sweeper<Key<V>>,
+    presets: Vec<Preset<V::Params>>,
+    /// Where the parameters are saved, `None` when drawing to a file
+    store: Option<Store>,
+    /// The last saved query
+    saved: String,
+    /// When the parameters last changed, while they are not saved
+    changed: Option<Instant>,
+}
+
+/// The canvas program drawing a visualization
+struct Visualized<V: Visualization> {
+    visualization: V,
+    params: V::Params,
+    cache: canvas::Cache,
+    clock: Clock,
+}
+
+/// Parameters from the query of the page
+#[cfg(target_arch = "wasm32")]
+fn shared_query() -> Option<String> {
+    let search = web_sys::window()?.location().search().ok()?;
+    Some(params::query_of(&search).to_owned())
+}
+
+/// The parameters saved when the app was last used, or the defaults
+/// Replaces the query of the page, without adding to the history
+#[cfg(target_arch = "wasm32")]
+fn share(query: &str) {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    let url = match query {
+        "" => window.location().pathname().unwrap_or_default(),
+        query => format!("?{query}"),
+    };
+    if let Ok(history) = window.history() {
+        let _ = history.replace_state_with_url(&wasm_bindgen::JsValue::NULL, "", Some(&url));
+    }
+}
+
+/// Runs the visualization in a window, or in the `iced_root` element on the web
+pub fn run<V: Visualization>() -> iced::Result {
+    let store = Store::new(V::TITLE, V::VERSION);
+
+    // Drawing to a file does not depend on the saved parameters
+    #[cfg(not(target_arch = "wasm32"))]
+    let (params, platform_specific) = {
+        let cli = Cli::parse(V::TITLE, &V::presets());
+        match cli.mode {
+            Mode::Window => (
+                cli.params.unwrap_or_else(|| store.params()),
+                iced::window::PlatformSpecific,
+            ),
+            Mode::Render { output, size } => {
+                return render::<V>(cli.params.unwrap_or_default(), &output, size)
+            }
+            Mode::Record(options) => return record::<V>(cli.params.unwrap_or_default(), options),
+        }
+    };
+
+    #[cfg(target_arch = "wasm32")]
+    let (params, platform_specific) = {
+        std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+
+        // A link gives all the parameters, the ones it does not have are the defaults
+        let params = match shared_query().filter(|q| !q.is_empty()) {
+            Some(query) => {
+                let mut params = V::Params::default();
+                if let Err(ignored) = params.set_query(&query) {
+                    let message = format!("Ignored invalid parameters: {ignored}");
+                    web_sys::console::warn_1(&message.into());
+                }
+                params
+            }
+            None => store.params(),
+        };
+
+        let platform_specific = iced::window::PlatformSpecific {
+            target: Some("iced_root".into()),
+        };
+        (params, platform_specific)
+    };
+
+    App::<V>::run(iced::Settings {
+        antialiasing: true,
+        window: iced::window::Settings {
+            platform_specific,
+            ..Default::default()
+        },
+        ..iced::Settings::with_flags((params, Some(store)))
+    })
+}
+
+/// Draws the parameters once to the SVG or PNG `output`, instead of opening a window
+#[cfg(not(target_arch = "wasm32"))]
+fn render<V: Visualization>(params: V::Params, output: &Path, size: Size<u32>) -> iced::Result {
+    let (app, _) = App::<V>::new((params, None));
+    let result = match output.extension().and_then(|e| e.to_str()) {
+        Some("svg") => std::fs::write(
+            output,
+            svg::draw_program::<_, Message<Key<V>>>(&app.canvas, size),
+        )
+        .map_err(Into::into),
+        _ => record::write_png(
+            output,
+            &raster::draw_program::<_, Message<Key<V>>>(&app.canvas, size),
+        ),
+    };
+
+    if let Err(e) = result {
+        eprintln!("Could not render {}: {e}", output.display());
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Draws the frames of a recording asked for on the command line, instead of opening a window
+#[cfg(not(target_arch = "wasm32"))]
+fn record<V: Visualization>(params: V::Params, options: RecordOptions<Key<V>>) -> iced::Result {
+    let (mut app, _) = App::<V>::new((params, None));
+    let result = options.record(|frame| {
+        if let Some((key, value)) = frame.parameter {
+            let _ = app.update(Message::Set(key, Value::Float(value)));
+        }
+        app.canvas.clock.set_time(frame.time);
+        raster::draw_program::<_, Message<Key<V>>>(&app.canvas, options.size)
+    });
+
+    if let Err(e) = result {
+        eprintln!("Could not record {}: {e}", options.output.display());
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+impl<V: Visualization> App<V> {
+    /// Shares and saves the parameters, if they changed since they were last saved
+    fn save(&mut self) {
+        self.changed = None;
+        let query = self.canvas.params.to_query();
+        if query == self.saved {
+            return;
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        share(&query);
+
+        if let Some(store) = &self.store {
+            store.save(&query);
+        }
+        self.saved = query;
+    }
+}
+
+impl<V: Visualization> Application for App<V> {
+    type Executor = executor::Default;
+    type Message = Message<Key<V>>;
+    type Theme = Theme;
+    type Flags = (V::Params, Option<Store>);
+
+    fn new((params, store): Self::Flags) -> (Self, Command<Self::Message>) {
+        let mut visualization = V::default();
+        visualization.update(&params, None);
+
+        let app = Self {
+            sweeper: V::sweeper(&params),
+            presets: V::presets(),
+            saved: params.to_query(),
+            changed: None,
+            store,
+            canvas: Visualized {
+                visualization,
+                params,
+                cache: canvas::Cache::new(),
+                clock: Clock::new(),
+            },
+        };
+
+        (app, Command::none())
+    }
+
+    fn title(&self) -> String {
+        V::TITLE.into()
+    }
+
+    fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
+        let canvas = &mut self.canvas;
+        match message {
+            Message::Set(key, value) => {
+                canvas.params.set(key, value);
+                canvas.visualization.update(&canvas.params, Some(key));
+            }
+            Message::SetAll(values) => {
+                for (key, value) in values {
+                    canvas.params.set(key, value);
+                    canvas.visualization.update(&canvas.params, Some(key));
+                }
+            }
+            Message::Preset(index) => {
+                canvas.params = self.presets[index].params.clone();
+                canvas.visualization.update(&canvas.params, None);
+            }
+            Message::Reset => {
+                canvas.params = V::Params::default();
+                canvas.visualization.update(&canvas.params, None);
+            }
+            Message::Sweep(m) => {
+                let params = &canvas.params;
+                return match self.sweeper.update(m, |&k| params.range(k)) {
+                    Some((key, value)) => self.update(Message::Set(key, Value::Float(value))),
+                    None => Command::none(),
+                };
+            }
+            // Only the animated layer changes, the cache stays valid
+            Message::Clock(m) => {
+                canvas.clock.update(m);
+                return Command::none();
+            }
+            Message::Save(now) => {
+                if self
+                    .changed
+                    .is_some_and(|changed| now.duration_since(changed) >= SAVE_DELAY)
+                {
+                    self.save();
+                }
+                return Command::none();
+            }
+        }
+
+        canvas.cache.clear();
+        self.changed = Some(Instant::now());
+
+        Command::none()
+    }
+
+    fn subscription(&self) -> Subscription<Self::Message> {
+        let mut subscriptions = vec![self.sweeper.subscription().map(Message::Sweep)];
+        if V::ANIMATED {
+            subscriptions.push(self.canvas.clock.subscription().map(Message::Clock));
+        }
+        if self.changed.is_some() {
+            subscriptions.push(window::frames().map(Message::Save));
+        }
+        Subscription::batch(subscriptions)
+    }
+
+    fn view(&self) -> iced::Element<'_, Self::Message> {
+        let mut top = Vec::new();
+        if !self.presets.is_empty() {
+            top.push(params::preset_list(
+                &self.presets,
+                &self.canvas.params,
+                Message::Preset,
+            ));
+        }
+        top.push(
+            button(text("Reset to defaults"))
+                .on_press(Message::Reset)
+                .into(),
+        );
+
+        let mut children = vec![
+            Row::with_children(top).spacing(10).into(),
+            params::panel(&self.canvas.params, Message::Set),
+        ];
+        if V::ANIMATED {
+            children.push(self.canvas.clock.controls(Message::Clock));
+        }
+        children.push(self.sweeper.controls(Message::Sweep));
+        children.push(
+            iced::widget::canvas(&self.canvas)
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .into(),
+        );
+
+        Column::with_children(children).padding(5).into()
+    }
+}
+
+/// State of the canvas between its events
+struct CanvasState<K> {
+    handles: HandleState<K>,
+    /// Last position of a drag of the canvas started away from the handles
+    dragged_from: Option<Point>,
+    /// The plotter when a handle keeping the view was grabbed, while it is dragged
+    frozen: Option<Plotter>,
+}
+
+impl<K> Default for CanvasState<K> {
+    fn default() -> Self {
+        Self {
+            handles: HandleState::default(),
+            dragged_from: None,
+            frozen: None,
+        }
+    }
+}
+
+/// Position in the canvas of a mouse or touch event, when it is on the canvas
+fn event_position(event: &Event, bounds: Rectangle, cursor: Cursor) -> Option<Point> {
+    match event {
+        Event::Touch(
+            touch::Event::FingerPressed { position, .. }
+            | touch::Event::FingerMoved { position, .. },
+        ) => {
+            let position = Point::new(position.x - bounds.x, position.y - bounds.y);
+            Rectangle::with_size(bounds.size())
+                .contains(position)
+                .then_some(position)
+        }
+        _ => cursor.position_in(&bounds),
+    }
+}
+
+impl<V: Visualization> canvas::Program<Message<Key<V>>> for Visualized<V> {
+    type State = CanvasState<Key<V>>;
+
+    fn update(
+        &self,
+        state: &mut Self::State,
+        event: Event,
+        bounds: Rectangle,
+        cursor: Cursor,
+    ) -> (event::Status, Option<Message<Key<V>>>) {
+        let plotter = state
+            .frozen
+            .take()
+            .unwrap_or_else(|| self.visualization.plotter(&self.params, bounds.size()));
+        let handles = self.visualization.handles(&self.params);
+        let (status, drag) = handles.update(&mut state.handles, &plotter, event, bounds, cursor);
+        if handles.keeps_view(&state.handles) {
+            state.frozen = Some(plotter.clone());
+        }
+
+        if status == event::Status::Ignored {
+            let position = event_position(&event, bounds, cursor);
+            let values = match event {
+                Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
+                | Event::Touch(touch::Event::FingerPressed { .. }) => {
+                    let values = position
+                        .map(|p| {
+                            self.visualization
+                                .click(&self.params, plotter.world_coord(p))
+                        })
+                        .unwrap_or_default();
+                    // A press that sets nothing starts dragging the canvas
+                    if values.is_empty() {
+                        state.dragged_from = position;
+                    }
+                    values
+                }
+                Event::Mouse(mouse::Event::CursorMoved { .. })
+                | Event::Touch(touch::Event::FingerMoved { .. }) => {
+                    match (state.dragged_from, position) {
+                        (Some(from), Some(to)) => {
+                            state.dragged_from = Some(to);
+                            self.visualization.drag_canvas(
+                                &self.params,
+                                &plotter,
+                                plotter.world_coord(from),
+                                plotter.world_coord(to),
+                            )
+                        }
+                        _ => Vec::new(),
+                    }
+                }
+                Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
+                | Event::Touch(
+                    touch::Event::FingerLifted { .. } | touch::Event::FingerLost { .. },
+                ) => {
+                    state.dragged_from = None;
+                    Vec::new()
+                }
+                _ => Vec::new(),
+            };
+            if !values.is_empty() {
+                return (event::Status::Captured, Some(Message::SetAll(values)));
+            }
+        }
+
+        let message = drag.and_then(|Drag { id, position }| {
+            self.visualization
+                .drag(&self.params, id, position)
+                .map(|value| Message::Set(id, value))
+        });
+
+        (status, message)
+    }
+
+    fn mouse_interaction(
+        &self,
+        state: &Self::State,
+        _bounds: Rectangle,
+        _cursor: Cursor,
+    ) -> mouse::Interaction {
+        self.visualization
+            .handles(&self.params)
+            .mouse_interaction(&state.handles)
+    }
+
+    fn draw(
+        &self,
+        state: &Self::State,
+        _theme: &Theme,
+        bounds: Rectangle,
+        cursor: Cursor,
+    ) -> Vec<Geometry> {
+        let plotter = state
+            .frozen
+            .clone()
+            .unwrap_or_else(|| self.visualization.plotter(&self.params, bounds.size()));
+
+        let mut layers = vec![self.cache.draw(bounds.size(), |frame| {
+            self.visualization.draw(&self.params, frame, &plotter)
+        })];
+
+        if V::ANIMATED {
+            layers.push(animation::layer(
+                bounds.size(),
+                self.clock.time(),
+                |frame, time| {
+                    self.visualization
+                        .animate(&self.params, frame, &plotter, time)
+                },
+            ));
+        }
+
+        let mut overlay = Frame::new(bounds.size());
+        self.visualization
+            .handles(&self.params)
+            .draw(&mut overlay, &plotter, &state.handles);
+
+        // Handles take precedence over the overlay
+        let cursor = cursor
+            .position_in(&bounds)
+            .filter(|_| !state.handles.is_active());
+        self.visualization
+            .overlay(&self.params, &mut overlay, &plotter, cursor);
+        layers.push(overlay.into_geometry());
+
+        layers
+    }
+}
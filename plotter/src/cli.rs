@@ -0,0 +1,364 @@
+//! Command line of the native binaries.
+//!
+//! Every parameter has a flag named like in the shared links, e.g. `--multiplier 59`,
+//! `--params` takes a whole link and `--preset` one of the presets of the post. The window then
+//! opens with these parameters, unless they are drawn to a file: once with `--render`, or as an
+//! animation with `--record`.
+
+use crate::{
+    params::{query_of, slug, Kind, Preset, Spec, Value},
+    record::{self, RecordOptions},
+    Parameters,
+};
+use clap::{AppSettings, Arg, Command, ErrorKind};
+use iced_graphics::Size;
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+/// What to do with the parameters
+pub enum Mode<K> {
+    Window,
+    /// Draws the parameters once to an SVG or a PNG
+    Render {
+        output: PathBuf,
+        size: Size<u32>,
+    },
+    Record(RecordOptions<K>),
+}
+
+pub struct Cli<P: Parameters> {
+    /// The parameters from the defaults, `None` when the command line sets none
+    pub params: Option<P>,
+    pub mode: Mode<P::Key>,
+}
+
+fn parse_size(text: &str) -> Result<Size<u32>, String> {
+    let invalid = || format!("expected <WIDTH>x<HEIGHT>, got {text}");
+
+    let (w, h) = text.split_once('x').ok_or_else(invalid)?;
+    let (w, h): (u32, u32) = (
+        w.parse().map_err(|_| invalid())?,
+        h.parse().map_err(|_| invalid())?,
+    );
+    if w == 0 || h == 0 {
+        return Err("the size must not be zero".into());
+    }
+
+    Ok(Size::new(w, h))
+}
+
+fn parse_render(text: &str) -> Result<PathBuf, String> {
+    match Path::new(text).extension().and_then(|e| e.to_str()) {
+        Some("svg" | "png") => Ok(text.into()),
+        _ => Err("expected a .svg or .png file".into()),
+    }
+}
+
+fn value_name(kind: &Kind) -> &'static str {
+    match kind {
+        Kind::Float { .. } => "NUMBER",
+        Kind::Int { .. } => "INTEGER",
+        Kind::Toggle => "BOOL",
+        Kind::Choice(_) => "CHOICE",
+    }
+}
+
+/// A number of the parameter in shown units, rounded like in the panel
+fn shown(spec: &Spec, value: f64) -> String {
+    let precision = match spec.kind {
+        Kind::Int { .. } => 0,
+        _ => spec.precision,
+    };
+    format!("{:.*}", precision, value * spec.scale)
+}
+
+/// Label of the parameter, its options and its default value, numbers being in shown units
+fn help(spec: &Spec, default: Value) -> String {
+    match &spec.kind {
+        Kind::Choice(options) => {
+            let options: Vec<_> = options.iter().map(|o| slug(o)).collect();
+            format!(
+                "{} [default: {}] [possible values: {}]",
+                spec.label,
+                spec.encode(default),
+                options.join(", ")
+            )
+        }
+        Kind::Toggle => format!("{} [default: {}]", spec.label, spec.encode(default)),
+        Kind::Float { .. } | Kind::Int { .. } => format!(
+            "{} [default: {}{}]",
+            spec.label,
+            shown(spec, default.as_f64()),
+            spec.unit
+        ),
+    }
+}
+
+/// What a value of the parameter should look like, for the errors
+fn expected(spec: &Spec) -> String {
+    match &spec.kind {
+        Kind::Float { .. } | Kind::Int { .. } => {
+            let range = spec.range();
+            format!(
+                ", expected a number from {} to {}{}",
+                shown(spec, *range.start()),
+                shown(spec, *range.end()),
+                spec.unit
+            )
+        }
+        Kind::Toggle => ", expected true or false".into(),
+        Kind::Choice(_) => String::new(),
+    }
+}
+
+impl<P> Cli<P>
+where
+    P: Parameters + Default + Clone,
+{
+    /// Parses the command line, exits with the usage when it is invalid
+    pub fn parse(title: &str, presets: &[Preset<P>]) -> Self {
+        Self::try_parse_from(title, presets, std::env::args_os()).unwrap_or_else(|e| e.exit())
+    }
+
+    pub fn try_parse_from<I, T>(title: &str, presets: &[Preset<P>], args: I) -> clap::Result<Self>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let mut params = P::default();
+        let names: Vec<_> = P::KEYS.iter().map(|k| slug(&k.to_string())).collect();
+        let helps: Vec<_> = P::KEYS
+            .iter()
+            .map(|&k| help(&params.spec(k), params.get(k)))
+            .collect();
+        let numeric = params.numeric();
+        let sweepable: Vec<_> = numeric.iter().map(|k| slug(&k.to_string())).collect();
+        let preset_names: Vec<_> = presets
+            .iter()
+            .map(|p| slug(&p.name.to_lowercase()))
+            .collect();
+
+        let mut command = Command::new(title)
+            .setting(AppSettings::DeriveDisplayOrder)
+            .allow_negative_numbers(true)
+            .arg(
+                Arg::new("params").long("params").value_name("LINK").help(
+                    "Parameters of a shared link, or its query like modulo=300&multiplier=59",
+                ),
+            )
+            .arg(
+                Arg::new("preset")
+                    .long("preset")
+                    .value_name("NAME")
+                    .possible_values(preset_names.iter().map(String::as_str))
+                    .hide(presets.is_empty())
+                    .help("Starts from a preset, before the other parameters are set"),
+            )
+            .arg(
+                Arg::new("render")
+                    .long("render")
+                    .value_name("PATH")
+                    .value_parser(parse_render)
+                    .conflicts_with("record")
+                    .help(
+                        "Draws the parameters to a .svg or .png file instead of opening a window",
+                    ),
+            )
+            .arg(
+                Arg::new("size")
+                    .long("size")
+                    .value_name("WxH")
+                    .value_parser(parse_size)
+                    .default_value("800x600")
+                    .help("Size of the rendered image or of the recorded frames, in pixels"),
+            )
+            .next_help_heading(Some("PARAMETERS"));
+
+        for ((&key, name), help) in P::KEYS.iter().zip(&names).zip(&helps) {
+            command = command.arg(
+                Arg::new(name.as_str())
+                    .long(name)
+                    .value_name(value_name(&params.spec(key).kind))
+                    .help(help.as_str()),
+            );
+        }
+
+        let mut command = command
+            .next_help_heading(Some("RECORDING"))
+            .args(record::args(&sweepable));
+        let matches = command.try_get_matches_from_mut(args)?;
+
+        if let Some(name) = matches.get_one::<String>("preset") {
+            if let Some(index) = preset_names.iter().position(|n| n == name) {
+                params = presets[index].params.clone();
+            }
+        }
+
+        if let Some(link) = matches.get_one::<String>("params") {
+            if let Err(invalid) = params.set_query(query_of(link)) {
+                return Err(command.error(
+                    ErrorKind::InvalidValue,
+                    format!("invalid parameters in --params: {invalid}"),
+                ));
+            }
+        }
+
+        // In the order of the keys, as a range can depend on the parameters before it
+        for (&key, name) in P::KEYS.iter().zip(&names) {
+            let Some(text) = matches.get_one::<String>(name) else {
+                continue;
+            };
+            let spec = params.spec(key);
+            match spec.decode(text) {
+                Some(value) => params.set(key, value),
+                None => {
+                    return Err(command.error(
+                        ErrorKind::InvalidValue,
+                        format!("invalid value '{text}' for '--{name}'{}", expected(&spec)),
+                    ))
+                }
+            }
+        }
+
+        let given = ["preset", "params"]
+            .into_iter()
+            .chain(names.iter().map(String::as_str))
+            .any(|id| matches.contains_id(id));
+
+        let size = *matches
+            .get_one::<Size<u32>>("size")
+            .expect("the size has a default");
+        let mode = if let Some(output) = matches.get_one::<PathBuf>("render") {
+            Mode::Render {
+                output: output.clone(),
+                size,
+            }
+        } else if let Some(options) =
+            RecordOptions::from_matches(&matches, size, &numeric, |&k| params.spec(k))
+        {
+            Mode::Record(options)
+        } else {
+            Mode::Window
+        };
+
+        Ok(Self {
+            params: given.then_some(params),
+            mode,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::Parameters;
+    use std::f64::consts::{FRAC_PI_2, PI};
+
+    crate::parameters! {
+        struct Params: Param |params| {
+            count: u64 = 10 => Spec::int("Count", 3..=300),
+            yaw: f64 = 0.6 => Spec::float("Yaw", 0.0..=FRAC_PI_2)
+                .scale(180. / PI)
+                .precision(1)
+                .unit("°"),
+            length: f64 = 1. => Spec::float("Length", 0.5..=params.count as f64),
+            labels: bool = true => Spec::toggle("Labels"),
+        }
+    }
+
+    fn parse(args: &[&str]) -> clap::Result<Cli<Params>> {
+        Cli::try_parse_from("test", &[], ["test"].iter().chain(args))
+    }
+
+    fn params(args: &[&str]) -> Params {
+        parse(args).unwrap().params.unwrap()
+    }
+
+    fn error(args: &[&str]) -> String {
+        match parse(args) {
+            Ok(_) => panic!("{args:?} should be rejected"),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn flags_in_shown_units() {
+        assert!(parse(&[]).unwrap().params.is_none());
+
+        let set = params(&["--yaw", "45", "--count", "20", "--length", "15"]);
+        assert!((set.yaw - PI / 4.).abs() < 1e-12);
+        assert_eq!((set.count, set.length), (20, 15.));
+        assert!(!params(&["--labels", "false"]).labels);
+    }
+
+    #[test]
+    fn out_of_range_rejected() {
+        let message = error(&["--yaw", "95"]);
+        assert!(
+            message.contains("invalid value '95' for '--yaw'"),
+            "{message}"
+        );
+        assert!(
+            message.contains("expected a number from 0.0 to 90.0°"),
+            "{message}"
+        );
+
+        assert!(error(&["--count", "2"]).contains("expected a number from 3 to 300"));
+        error(&["--count", "2.5"]);
+        error(&["--yaw", "NaN"]);
+        error(&["--labels", "maybe"]);
+        // The range of the length follows the count, which is set first
+        error(&["--length", "15"]);
+        assert_eq!(params(&["--length", "15", "--count", "15"]).length, 15.);
+    }
+
+    #[test]
+    fn params_link() {
+        let set = params(&["--params", "https://example.com/post?count=20&yaw=30"]);
+        assert_eq!(set.count, 20);
+        assert!((set.yaw - PI / 6.).abs() < 1e-12);
+        assert_eq!(params(&["--params", "count=20"]).count, 20);
+
+        // The flags are set after the link
+        assert_eq!(params(&["--params", "count=20", "--count", "30"]).count, 30);
+
+        let message = error(&["--params", "yaw=100"]);
+        assert!(
+            message.contains("invalid parameters in --params"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn rounded_defaults() {
+        let params = Params::default();
+        let help = |key| help(&params.spec(key), params.get(key));
+        assert_eq!(help(Param::Yaw), "Yaw [default: 34.4°]");
+        assert_eq!(help(Param::Count), "Count [default: 10]");
+        assert_eq!(help(Param::Length), "Length [default: 1.00]");
+        assert_eq!(help(Param::Labels), "Labels [default: true]");
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("800x600"), Ok(Size::new(800, 600)));
+        assert_eq!(parse_size("1x1"), Ok(Size::new(1, 1)));
+        for invalid in [
+            "800",
+            "800x",
+            "x600",
+            "800*600",
+            "-800x600",
+            "800x600x2",
+            "ax600",
+        ] {
+            assert!(parse_size(invalid).is_err(), "{invalid}");
+        }
+        assert_eq!(
+            parse_size("0x600"),
+            Err("the size must not be zero".to_owned())
+        );
+    }
+}
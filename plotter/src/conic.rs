@@ -0,0 +1,612 @@
+//! Conic sections in their general form.
+
+use crate::{linspace, vec2d, Path2D, Plotter, Vector2D};
+use iced_graphics::widget::canvas::path::Builder;
+
+/// Maximum number of samples used to draw an unbounded branch
+const MAX_SAMPLES: usize = 10_000;
+
+/// An infinite line going through `point`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Line {
+    pub point: Vector2D,
+    pub direction: Vector2D,
+}
+
+impl Line {
+    pub fn new(point: Vector2D, direction: Vector2D) -> Self {
+        Self {
+            point,
+            direction: direction.normalize(),
+        }
+    }
+
+    /// Signed distance to the line, positive on the left of `direction`
+    pub fn distance(&self, p: Vector2D) -> f64 {
+        (p - self.point).dot(self.direction.perp())
+    }
+}
+
+///
+/// `a*x^2 + b*x*y + c*y^2 + d*x + e*y + f = 0`
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Conic {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConicKind {
+    Ellipse,
+    Parabola,
+    Hyperbola,
+    /// Points, lines, pairs of lines or empty sets
+    Degenerate,
+}
+
+///
+/// A conic expressed in its own frame: `rotation` is the angle of the principal axis from the
+/// x axis.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Canonical {
+    /// `a` is the semi-axis along the principal axis, it may be smaller than `b`
+    Ellipse {
+        center: Vector2D,
+        a: f64,
+        b: f64,
+        rotation: f64,
+    },
+    /// The principal axis is the transverse one, going through both vertices
+    Hyperbola {
+        center: Vector2D,
+        a: f64,
+        b: f64,
+        rotation: f64,
+    },
+    /// Opens in the direction of the principal axis, with the focus at `focal_length` from the
+    /// vertex
+    Parabola {
+        vertex: Vector2D,
+        focal_length: f64,
+        rotation: f64,
+    },
+    Degenerate,
+}
+
+fn direction(angle: f64) -> Vector2D {
+    vec2d(angle.cos(), angle.sin())
+}
+
+impl Conic {
+    pub fn new(a: f64, b: f64, c: f64, d: f64, e: f64, f: f64) -> Self {
+        Self { a, b, c, d, e, f }
+    }
+
+    /// `y = a*x^2 + b*x + c`
+    pub fn parabola(a: f64, b: f64, c: f64) -> Self {
+        Self::new(a, 0., 0., b, -1., c)
+    }
+
+    /// Ellipse of semi-axes `a` and `b`, `a` being along the direction at `rotation` from the x
+    /// axis
+    pub fn ellipse(center: Vector2D, a: f64, b: f64, rotation: f64) -> Self {
+        Self::from_canonical(center, 1. / (a * a), 1. / (b * b), rotation)
+    }
+
+    /// Hyperbola with its vertices at `a` from the center in the direction at `rotation` from
+    /// the x axis, and asymptotes of slope `b/a` relative to that direction
+    pub fn hyperbola(center: Vector2D, a: f64, b: f64, rotation: f64) -> Self {
+        Self::from_canonical(center, 1. / (a * a), -1. / (b * b), rotation)
+    }
+
+    /// `p*u^2 + q*v^2 = 1` in the frame centered on `center` and rotated by `rotation`
+    fn from_canonical(center: Vector2D, p: f64, q: f64, rotation: f64) -> Self {
+        let (sin, cos) = rotation.sin_cos();
+
+        // u = cos*(x - x0) + sin*(y - y0), v = -sin*(x - x0) + cos*(y - y0)
+        let a = p * cos * cos + q * sin * sin;
+        let b = 2. * (p - q) * sin * cos;
+        let c = p * sin * sin + q * cos * cos;
+
+        let (x0, y0) = (center.x(), center.y());
+        Self::new(
+            a,
+            b,
+            c,
+            -2. * a * x0 - b * y0,
+            -2. * c * y0 - b * x0,
+            a * x0 * x0 + b * x0 * y0 + c * y0 * y0 - 1.,
+        )
+    }
+
+    ///
+    /// Locus of the points whose distance to `focus` is `eccentricity` times their distance to
+    /// `directrix`.
+    ///
+    pub fn from_focus_directrix(focus: Vector2D, directrix: Line, eccentricity: f64) -> Self {
+        let n = directrix.direction.perp();
+        let k = directrix.point.dot(n);
+        let e2 = eccentricity * eccentricity;
+        let (fx, fy) = (focus.x(), focus.y());
+        let (nx, ny) = (n.x(), n.y());
+
+        // |P - F|^2 = e^2 * (n.P - k)^2
+        Self::new(
+            1. - e2 * nx * nx,
+            -2. * e2 * nx * ny,
+            1. - e2 * ny * ny,
+            -2. * fx + 2. * e2 * k * nx,
+            -2. * fy + 2. * e2 * k * ny,
+            fx * fx + fy * fy - e2 * k * k,
+        )
+    }
+
+    pub fn eval(&self, p: Vector2D) -> f64 {
+        let (x, y) = (p.x(), p.y());
+        self.a * x * x + self.b * x * y + self.c * y * y + self.d * x + self.e * y + self.f
+    }
+
+    fn epsilon(&self) -> f64 {
+        let scale = [self.a, self.b, self.c, self.d, self.e, self.f]
+            .iter()
+            .fold(0f64, |m, v| m.max(v.abs()));
+        scale * 1e-12
+    }
+
+    pub fn canonical(&self) -> Canonical {
+        let eps = self.epsilon();
+
+        // Rotating by this angle removes the xy term
+        let rotation = 0.5 * self.b.atan2(self.a - self.c);
+        let (sin, cos) = rotation.sin_cos();
+
+        let a = self.a * cos * cos + self.b * cos * sin + self.c * sin * sin;
+        let c = self.a * sin * sin - self.b * cos * sin + self.c * cos * cos;
+        let d = self.d * cos + self.e * sin;
+        let e = -self.d * sin + self.e * cos;
+        let f = self.f;
+
+        let to_world = |u: f64, v: f64| direction(rotation) * u + direction(rotation).perp() * v;
+
+        match (a.abs() <= eps, c.abs() <= eps) {
+            (true, true) => Canonical::Degenerate,
+            (false, false) => {
+                let (u0, v0) = (-d / (2. * a), -e / (2. * c));
+                // a*(u - u0)^2 + c*(v - v0)^2 = k
+                let k = a * u0 * u0 + c * v0 * v0 - f;
+                if k.abs() <= eps {
+                    return Canonical::Degenerate;
+                }
+
+                let (p, q) = (k / a, k / c);
+                let center = to_world(u0, v0);
+                match (p > 0., q > 0.) {
+                    (true, true) => Canonical::Ellipse {
+                        center,
+                        a: p.sqrt(),
+                        b: q.sqrt(),
+                        rotation,
+                    },
+                    (true, false) => Canonical::Hyperbola {
+                        center,
+                        a: p.sqrt(),
+                        b: (-q).sqrt(),
+                        rotation,
+                    },
+                    (false, true) => Canonical::Hyperbola {
+                        center,
+                        a: q.sqrt(),
+                        b: (-p).sqrt(),
+                        rotation: rotation + std::f64::consts::FRAC_PI_2,
+                    },
+                    (false, false) => Canonical::Degenerate,
+                }
+            }
+            (false, true) => {
+                if e.abs() <= eps {
+                    return Canonical::Degenerate;
+                }
+                // v = -(a*u^2 + d*u + f) / e, the axis is along v
+                let u0 = -d / (2. * a);
+                let v0 = -(a * u0 * u0 + d * u0 + f) / e;
+                let curvature = -a / e;
+                let axis = if curvature > 0. {
+                    rotation + std::f64::consts::FRAC_PI_2
+                } else {
+                    rotation - std::f64::consts::FRAC_PI_2
+                };
+
+                Canonical::Parabola {
+                    vertex: to_world(u0, v0),
+                    focal_length: 1. / (4. * curvature.abs()),
+                    rotation: axis,
+                }
+            }
+            (true, false) => {
+                if d.abs() <= eps {
+                    return Canonical::Degenerate;
+                }
+                // u = -(c*v^2 + e*v + f) / d, the axis is along u
+                let v0 = -e / (2. * c);
+                let u0 = -(c * v0 * v0 + e * v0 + f) / d;
+                let curvature = -c / d;
+                let axis = if curvature > 0. {
+                    rotation
+                } else {
+                    rotation + std::f64::consts::PI
+                };
+
+                Canonical::Parabola {
+                    vertex: to_world(u0, v0),
+                    focal_length: 1. / (4. * curvature.abs()),
+                    rotation: axis,
+                }
+            }
+        }
+    }
+
+    pub fn kind(&self) -> ConicKind {
+        match self.canonical() {
+            Canonical::Ellipse { .. } => ConicKind::Ellipse,
+            Canonical::Hyperbola { .. } => ConicKind::Hyperbola,
+            Canonical::Parabola { .. } => ConicKind::Parabola,
+            Canonical::Degenerate => ConicKind::Degenerate,
+        }
+    }
+
+    pub fn eccentricity(&self) -> Option<f64> {
+        match self.canonical() {
+            Canonical::Ellipse { a, b, .. } => {
+                let (major, minor) = if a >= b { (a, b) } else { (b, a) };
+                Some((1. - (minor * minor) / (major * major)).sqrt())
+            }
+            Canonical::Hyperbola { a, b, .. } => Some((1. + (b * b) / (a * a)).sqrt()),
+            Canonical::Parabola { .. } => Some(1.),
+            Canonical::Degenerate => None,
+        }
+    }
+
+    /// Center of symmetry, parabolas do not have one
+    pub fn center(&self) -> Option<Vector2D> {
+        match self.canonical() {
+            Canonical::Ellipse { center, .. } | Canonical::Hyperbola { center, .. } => Some(center),
+            _ => None,
+        }
+    }
+
+    pub fn foci(&self) -> Vec<Vector2D> {
+        match self.canonical() {
+            Canonical::Ellipse {
+                center,
+                a,
+                b,
+                rotation,
+            } => {
+                let (major, minor, axis) = if a >= b {
+                    (a, b, direction(rotation))
+                } else {
+                    (b, a, direction(rotation).perp())
+                };
+                let c = (major * major - minor * minor).sqrt();
+                vec![center - axis * c, center + axis * c]
+            }
+            Canonical::Hyperbola {
+                center,
+                a,
+                b,
+                rotation,
+            } => {
+                let c = (a * a + b * b).sqrt();
+                let axis = direction(rotation);
+                vec![center - axis * c, center + axis * c]
+            }
+            Canonical::Parabola {
+                vertex,
+                focal_length,
+                rotation,
+            } => vec![vertex + direction(rotation) * focal_length],
+            Canonical::Degenerate => Vec::new(),
+        }
+    }
+
+    /// The points where the principal axis crosses the curve
+    pub fn vertices(&self) -> Vec<Vector2D> {
+        match self.canonical() {
+            Canonical::Ellipse {
+                center,
+                a,
+                b,
+                rotation,
+            } => {
+                let (major, axis) = if a >= b {
+                    (a, direction(rotation))
+                } else {
+                    (b, direction(rotation).perp())
+                };
+                vec![center - axis * major, center + axis * major]
+            }
+            Canonical::Hyperbola {
+                center,
+                a,
+                rotation,
+                ..
+            } => {
+                let axis = direction(rotation);
+                vec![center - axis * a, center + axis * a]
+            }
+            Canonical::Parabola { vertex, .. } => vec![vertex],
+            Canonical::Degenerate => Vec::new(),
+        }
+    }
+
+    /// Axes of symmetry, starting with the one going through the foci
+    pub fn axes(&self) -> Vec<Line> {
+        match self.canonical() {
+            Canonical::Ellipse {
+                center,
+                a,
+                b,
+                rotation,
+            } => {
+                let axis = if a >= b {
+                    direction(rotation)
+                } else {
+                    direction(rotation).perp()
+                };
+                vec![Line::new(center, axis), Line::new(center, axis.perp())]
+            }
+            Canonical::Hyperbola {
+                center, rotation, ..
+            } => {
+                let axis = direction(rotation);
+                vec![Line::new(center, axis), Line::new(center, axis.perp())]
+            }
+            Canonical::Parabola {
+                vertex, rotation, ..
+            } => vec![Line::new(vertex, direction(rotation))],
+            Canonical::Degenerate => Vec::new(),
+        }
+    }
+
+    pub fn asymptotes(&self) -> Vec<Line> {
+        match self.canonical() {
+            Canonical::Hyperbola {
+                center,
+                a,
+                b,
+                rotation,
+            } => {
+                let slope = b.atan2(a);
+                vec![
+                    Line::new(center, direction(rotation + slope)),
+                    Line::new(center, direction(rotation - slope)),
+                ]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Plotter {
+    /// Radius of a disk centered on the origin containing the whole viewport
+    fn view_radius(&self) -> f64 {
+        vec2d(self.width / 2., self.height / 2.).norm()
+    }
+
+    ///
+    /// Whether some of the segment `[p0, p1]` is inside the viewport, even when both ends are
+    /// outside. The segment is clipped by each side of the viewport in turn (Liang-Barsky).
+    ///
+    fn segment_visible(&self, p0: Vector2D, p1: Vector2D) -> bool {
+        let (half_width, half_height) = (self.width / 2., self.height / 2.);
+        let d = p1 - p0;
+
+        // Along the segment p0 + t*d, the part inside the viewport is t in [enter, leave]
+        let (mut enter, mut leave) = (0f64, 1f64);
+        let sides = [
+            (-d.x(), p0.x() + half_width),
+            (d.x(), half_width - p0.x()),
+            (-d.y(), p0.y() + half_height),
+            (d.y(), half_height - p0.y()),
+        ];
+        for (p, q) in sides {
+            if p == 0. {
+                // Parallel to the side, and outside of it
+                if q < 0. {
+                    return false;
+                }
+            } else if p < 0. {
+                enter = enter.max(q / p);
+            } else {
+                leave = leave.min(q / p);
+            }
+        }
+        enter <= leave
+    }
+
+    /// Number of samples for a curve of the given world length
+    fn samples(&self, length: f64) -> usize {
+        ((length * self.resolution as f64).ceil() as usize).clamp(2, MAX_SAMPLES)
+    }
+
+    ///
+    /// Like Plotter::path, but only keeps the segments that cross the viewport.
+    /// This avoids sending huge coordinates to the renderer for unbounded curves.
+    ///
+    fn clipped_path<I>(&self, builder: &mut Builder, points: I)
+    where
+        I: IntoIterator<Item = Vector2D>,
+    {
+        let mut previous: Option<Vector2D> = None;
+        let mut pen_at_previous = false;
+
+        for point in points {
+            if let Some(prev) = previous {
+                if self.segment_visible(prev, point) {
+                    if !pen_at_previous {
+                        builder.move_to(self.screen_coord(prev));
+                    }
+                    builder.line_to(self.screen_coord(point));
+                    pen_at_previous = true;
+                } else {
+                    pen_at_previous = false;
+                }
+            }
+
+            previous = Some(point);
+        }
+    }
+
+    /// Infinite line, clipped to the viewport
+    pub fn line(&self, line: Line) -> Path2D {
+        let reach = self.view_radius() + line.point.norm();
+
+        self.path([
+            line.point - line.direction * reach,
+            line.point + line.direction * reach,
+        ])
+    }
+
+    ///
+    /// Draws the part of the conic inside the viewport, both branches are drawn for hyperbolas.
+    /// Degenerate conics produce an empty path.
+    ///
+    pub fn conic(&self, conic: &Conic) -> Path2D {
+        match conic.canonical() {
+            Canonical::Ellipse {
+                center,
+                a,
+                b,
+                rotation,
+            } => self.rotated_ellipse(center.x(), center.y(), a, b, rotation),
+            Canonical::Hyperbola {
+                center,
+                a,
+                b,
+                rotation,
+            } => {
+                // Past t_max points are further from the center than any point of the viewport
+                let reach = self.view_radius() + center.norm();
+                let t_max = (reach / a.min(b)).max(1.).acosh();
+                let count = self.samples(2. * reach);
+
+                let u = direction(rotation);
+                let v = u.perp();
+                let branch = |side: f64| {
+                    linspace(-t_max, t_max, count)
+                        .map(move |t| center + u * (side * a * t.cosh()) + v * (b * t.sinh()))
+                };
+
+                Path2D::new(|builder| {
+                    self.clipped_path(builder, branch(1.));
+                    self.clipped_path(builder, branch(-1.));
+                })
+            }
+            Canonical::Parabola {
+                vertex,
+                focal_length,
+                rotation,
+            } => {
+                // The points of the curve are further than |v| from the vertex
+                let reach = self.view_radius() + vertex.norm();
+                let count = self.samples(4. * reach);
+
+                let u = direction(rotation);
+                let v = u.perp();
+                let points = linspace(-reach, reach, count)
+                    .map(|s| vertex + u * (s * s / (4. * focal_length)) + v * s);
+
+                Path2D::new(|builder| self.clipped_path(builder, points))
+            }
+            Canonical::Degenerate => Path2D::new(|_| ()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::FRAC_PI_6;
+
+    fn assert_near(actual: Vector2D, expected: Vector2D) {
+        assert!(
+            (actual - expected).norm() < 1e-9,
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    fn assert_same_points(actual: Vec<Vector2D>, expected: &[Vector2D]) {
+        assert_eq!(actual.len(), expected.len());
+        for e in expected {
+            assert!(
+                actual.iter().any(|a| (*a - *e).norm() < 1e-9),
+                "{e:?} not in {actual:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn rotated_ellipse() {
+        let center = vec2d(1., -2.);
+        let conic = Conic::ellipse(center, 5., 3., FRAC_PI_6);
+        assert_eq!(conic.kind(), ConicKind::Ellipse);
+        assert_near(conic.center().unwrap(), center);
+        assert!((conic.eccentricity().unwrap() - 0.8).abs() < 1e-9);
+
+        let axis = direction(FRAC_PI_6);
+        assert_same_points(conic.foci(), &[center + axis * 4., center - axis * 4.]);
+        assert_same_points(conic.vertices(), &[center + axis * 5., center - axis * 5.]);
+        for p in conic.vertices() {
+            assert!(conic.eval(p).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn hyperbola() {
+        let conic = Conic::hyperbola(vec2d(0., 0.), 3., 4., 0.);
+        assert_eq!(conic.kind(), ConicKind::Hyperbola);
+        assert_same_points(conic.foci(), &[vec2d(5., 0.), vec2d(-5., 0.)]);
+        assert_same_points(conic.vertices(), &[vec2d(3., 0.), vec2d(-3., 0.)]);
+        assert!((conic.eccentricity().unwrap() - 5. / 3.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn parabola() {
+        // y = x²/4 has its focus at (0, 1)
+        let conic = Conic::parabola(0.25, 0., 0.);
+        assert_eq!(conic.kind(), ConicKind::Parabola);
+        assert_same_points(conic.vertices(), &[vec2d(0., 0.)]);
+        assert_same_points(conic.foci(), &[vec2d(0., 1.)]);
+
+        assert_eq!(Conic::parabola(0., 1., 2.).kind(), ConicKind::Degenerate);
+    }
+
+    #[test]
+    fn focus_and_directrix() {
+        let focus = vec2d(2., 1.);
+        let directrix = Line::new(vec2d(0., -3.), vec2d(1., 1.));
+        for eccentricity in [0.5, 1., 2.] {
+            let conic = Conic::from_focus_directrix(focus, directrix, eccentricity);
+            assert!(conic.foci().iter().any(|f| (*f - focus).norm() < 1e-9));
+            assert!((conic.eccentricity().unwrap() - eccentricity).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn segments_crossing_the_view() {
+        let plotter = Plotter::new(100, 200., 200., 100.);
+        // Both ends are outside, the middle is inside
+        assert!(plotter.segment_visible(vec2d(-5., 0.), vec2d(5., 0.)));
+        assert!(plotter.segment_visible(vec2d(-5., -5.), vec2d(5., 5.)));
+        assert!(plotter.segment_visible(vec2d(0., 0.), vec2d(5., 5.)));
+        // Passing by a corner
+        assert!(!plotter.segment_visible(vec2d(0., 3.), vec2d(3., 0.)));
+        assert!(!plotter.segment_visible(vec2d(2., -5.), vec2d(2., 5.)));
+    }
+}
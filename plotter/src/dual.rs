@@ -0,0 +1,322 @@
+//! Forward-mode automatic differentiation.
+//!
+//! A [`Dual`] number carries a value together with its derivative with respect to a single
+//! variable. Writing a function once over [`Dual`] gives both `f(x)` and `f'(x)`, without having
+//! to derive (and possibly get wrong) the derivative by hand.
+
+use crate::{vec2d, Vector2D};
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dual {
+    pub value: f64,
+    pub derivative: f64,
+}
+
+impl Dual {
+    pub fn new(value: f64, derivative: f64) -> Self {
+        Self { value, derivative }
+    }
+
+    /// The variable we are differentiating against, `d/dx x = 1`
+    pub fn variable(x: f64) -> Self {
+        Self::new(x, 1.)
+    }
+
+    pub fn constant(c: f64) -> Self {
+        Self::new(c, 0.)
+    }
+
+    /// Applies `f` to the value, `df` being the derivative of `f` (chain rule)
+    fn chain(self, f: f64, df: f64) -> Self {
+        Self::new(f, df * self.derivative)
+    }
+
+    pub fn recip(self) -> Self {
+        self.chain(self.value.recip(), -1. / (self.value * self.value))
+    }
+
+    pub fn abs(self) -> Self {
+        self.chain(self.value.abs(), self.value.signum())
+    }
+
+    pub fn sqrt(self) -> Self {
+        let s = self.value.sqrt();
+        self.chain(s, 1. / (2. * s))
+    }
+
+    pub fn cbrt(self) -> Self {
+        let c = self.value.cbrt();
+        self.chain(c, 1. / (3. * c * c))
+    }
+
+    pub fn powi(self, n: i32) -> Self {
+        if n == 0 {
+            return Self::constant(1.);
+        }
+        self.chain(self.value.powi(n), n as f64 * self.value.powi(n - 1))
+    }
+
+    pub fn powf(self, n: f64) -> Self {
+        self.chain(self.value.powf(n), n * self.value.powf(n - 1.))
+    }
+
+    /// `self^exponent` where both may depend on the variable
+    pub fn pow(self, exponent: Dual) -> Self {
+        (exponent * self.ln()).exp()
+    }
+
+    pub fn exp(self) -> Self {
+        let e = self.value.exp();
+        self.chain(e, e)
+    }
+
+    pub fn ln(self) -> Self {
+        self.chain(self.value.ln(), 1. / self.value)
+    }
+
+    pub fn log(self, base: f64) -> Self {
+        self.ln() / base.ln()
+    }
+
+    pub fn sin(self) -> Self {
+        self.chain(self.value.sin(), self.value.cos())
+    }
+
+    pub fn cos(self) -> Self {
+        self.chain(self.value.cos(), -self.value.sin())
+    }
+
+    pub fn tan(self) -> Self {
+        let t = self.value.tan();
+        self.chain(t, 1. + t * t)
+    }
+
+    pub fn asin(self) -> Self {
+        self.chain(
+            self.value.asin(),
+            1. / (1. - self.value * self.value).sqrt(),
+        )
+    }
+
+    pub fn acos(self) -> Self {
+        self.chain(
+            self.value.acos(),
+            -1. / (1. - self.value * self.value).sqrt(),
+        )
+    }
+
+    pub fn atan(self) -> Self {
+        self.chain(self.value.atan(), 1. / (1. + self.value * self.value))
+    }
+
+    /// Angle of the point `(other, self)`, like [`f64::atan2`]
+    pub fn atan2(self, other: Dual) -> Self {
+        let (y, x) = (self.value, other.value);
+        let r2 = x * x + y * y;
+        Self::new(
+            y.atan2(x),
+            (x * self.derivative - y * other.derivative) / r2,
+        )
+    }
+
+    pub fn sinh(self) -> Self {
+        self.chain(self.value.sinh(), self.value.cosh())
+    }
+
+    pub fn cosh(self) -> Self {
+        self.chain(self.value.cosh(), self.value.sinh())
+    }
+
+    pub fn tanh(self) -> Self {
+        let t = self.value.tanh();
+        self.chain(t, 1. - t * t)
+    }
+
+    pub fn hypot(self, other: Dual) -> Self {
+        (self * self + other * other).sqrt()
+    }
+}
+
+impl From<f64> for Dual {
+    fn from(c: f64) -> Self {
+        Self::constant(c)
+    }
+}
+
+impl Neg for Dual {
+    type Output = Dual;
+    fn neg(self) -> Dual {
+        Dual::new(-self.value, -self.derivative)
+    }
+}
+
+impl Add<Dual> for Dual {
+    type Output = Dual;
+    fn add(self, rhs: Dual) -> Dual {
+        Dual::new(self.value + rhs.value, self.derivative + rhs.derivative)
+    }
+}
+
+impl Sub<Dual> for Dual {
+    type Output = Dual;
+    fn sub(self, rhs: Dual) -> Dual {
+        Dual::new(self.value - rhs.value, self.derivative - rhs.derivative)
+    }
+}
+
+impl Mul<Dual> for Dual {
+    type Output = Dual;
+    fn mul(self, rhs: Dual) -> Dual {
+        Dual::new(
+            self.value * rhs.value,
+            self.derivative * rhs.value + self.value * rhs.derivative,
+        )
+    }
+}
+
+impl Div<Dual> for Dual {
+    type Output = Dual;
+    fn div(self, rhs: Dual) -> Dual {
+        Dual::new(
+            self.value / rhs.value,
+            (self.derivative * rhs.value - self.value * rhs.derivative) / (rhs.value * rhs.value),
+        )
+    }
+}
+
+macro_rules! scalar_ops {
+    ($($op_name:ident);* ;) => {
+        $(
+        paste::paste! {
+            impl [< $op_name:camel >]<f64> for Dual {
+                type Output = Dual;
+                fn $op_name(self, rhs: f64) -> Dual {
+                    self.$op_name(Dual::constant(rhs))
+                }
+            }
+
+            impl [< $op_name:camel >]<Dual> for f64 {
+                type Output = Dual;
+                fn $op_name(self, rhs: Dual) -> Dual {
+                    Dual::constant(self).$op_name(rhs)
+                }
+            }
+
+            impl [< $op_name:camel Assign >]<Dual> for Dual {
+                fn [< $op_name _assign >](&mut self, rhs: Dual) {
+                    *self = (*self).$op_name(rhs);
+                }
+            }
+
+            impl [< $op_name:camel Assign >]<f64> for Dual {
+                fn [< $op_name _assign >](&mut self, rhs: f64) {
+                    *self = (*self).$op_name(rhs);
+                }
+            }
+        }
+        )*
+    };
+}
+
+scalar_ops! {
+    add;
+    sub;
+    mul;
+    div;
+}
+
+/// Evaluates `f(x)` and `f'(x)` at the same time
+pub fn eval<F>(f: F, x: f64) -> Dual
+where
+    F: Fn(Dual) -> Dual,
+{
+    f(Dual::variable(x))
+}
+
+pub fn derivative<F>(f: F, x: f64) -> f64
+where
+    F: Fn(Dual) -> Dual,
+{
+    eval(f, x).derivative
+}
+
+/// Unit tangent of the graph of `f` at `x`, pointing towards increasing `x`
+pub fn tangent<F>(f: F, x: f64) -> Vector2D
+where
+    F: Fn(Dual) -> Dual,
+{
+    vec2d(1., derivative(f, x)).normalize()
+}
+
+/// Unit normal of the graph of `f` at `x`, it is the tangent rotated by a quarter turn
+/// counter-clockwise
+pub fn normal<F>(f: F, x: f64) -> Vector2D
+where
+    F: Fn(Dual) -> Dual,
+{
+    tangent(f, x).perp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() <= 1e-9 * expected.abs().max(1.),
+            "{actual} != {expected}"
+        );
+    }
+
+    /// Central finite difference, as an independent check of the derivatives
+    fn finite_difference<F: Fn(f64) -> f64>(f: F, x: f64) -> f64 {
+        let h = 1e-6;
+        (f(x + h) - f(x - h)) / (2. * h)
+    }
+
+    #[test]
+    fn elementary_functions() {
+        for x in [-1.3, -0.2, 0.4, 1.1] {
+            assert_close(derivative(Dual::exp, x), x.exp());
+            assert_close(derivative(Dual::sin, x), x.cos());
+            assert_close(derivative(Dual::tan, x), 1. / (x.cos() * x.cos()));
+            assert_close(derivative(|x| x.powi(3), x), 3. * x * x);
+            assert_close(derivative(|x| x.powi(-2), x), -2. / (x * x * x));
+            assert_close(derivative(|x| x.powi(0), x), 0.);
+        }
+    }
+
+    #[test]
+    fn atan2() {
+        // Angle of the point (cos(t), 2 sin(t)) on an ellipse
+        let angle = |t: Dual| (2. * t.sin()).atan2(t.cos());
+        for t in [0.3, 1.2, 2.5, -2.] {
+            let expected = finite_difference(|t| (2. * t.sin()).atan2(t.cos()), t);
+            assert!((derivative(angle, t) - expected).abs() < 1e-6);
+            assert_close(eval(angle, t).value, (2. * t.sin()).atan2(t.cos()));
+        }
+    }
+
+    #[test]
+    fn bicycle_flat_curve() {
+        // The curve of bicycle_track, flat at 0 and 1
+        let f = |x: Dual| (-1. / (x * x)).exp() * (-1. / ((x - 1.) * (x - 1.))).exp();
+        for x in [0.2, 0.5, 0.7, 0.9] {
+            let value = eval(f, x).value;
+            let expected = value * (2. / (x * x * x) + 2. / ((x - 1.) * (x - 1.) * (x - 1.)));
+            assert_close(derivative(f, x), expected);
+        }
+    }
+
+    #[test]
+    fn tangent_and_normal() {
+        let t = tangent(|x| x * x, 1.);
+        assert_close(t.x(), 1. / 5f64.sqrt());
+        assert_close(t.y(), 2. / 5f64.sqrt());
+
+        let n = normal(|x| x * x, 1.);
+        assert_close(n.x(), -2. / 5f64.sqrt());
+        assert_close(n.y(), 1. / 5f64.sqrt());
+    }
+}
@@ -0,0 +1,187 @@
+//! Numerical envelopes of one-parameter families of curves.
+//!
+//! The envelope of a family `F(x, y, θ) = 0` is the set of points satisfying both
+//! `F(x, y, θ) = 0` and `∂F/∂θ(x, y, θ) = 0`. Derivatives with respect to the parameter are
+//! computed exactly with [`Dual`] numbers, the remaining ones are approximated.
+//!
+//! Both solvers work by continuation: the solutions for the previous values of `θ` are
+//! extrapolated to get the starting point for the next one, so `thetas` should be ordered and
+//! evenly spaced. Only the initial guess needs to be close to the envelope.
+
+use crate::{vec2d, Dual, Vector2D};
+
+const MAX_ITERATIONS: usize = 50;
+const TOLERANCE: f64 = 1e-10;
+
+fn step_size(x: f64) -> f64 {
+    1e-6 * (1. + x.abs())
+}
+
+fn converged(step: f64, x: f64) -> bool {
+    step.abs() <= TOLERANCE * (1. + x.abs())
+}
+
+/// Solves `F = ∂F/∂θ = 0` for a fixed `θ`, starting from `guess`
+fn solve_implicit<F>(f: &F, theta: f64, guess: Vector2D) -> Option<Vector2D>
+where
+    F: Fn(Dual, Dual, Dual) -> Dual,
+{
+    let (mut x, mut y) = (guess.x(), guess.y());
+
+    let d_theta = |x: f64, y: f64| f(x.into(), y.into(), Dual::variable(theta)).derivative;
+
+    for _ in 0..MAX_ITERATIONS {
+        let value = f(x.into(), y.into(), theta.into()).value;
+        let fx = f(Dual::variable(x), y.into(), theta.into()).derivative;
+        let fy = f(x.into(), Dual::variable(y), theta.into()).derivative;
+
+        let g = d_theta(x, y);
+        let (hx, hy) = (step_size(x), step_size(y));
+        let gx = (d_theta(x + hx, y) - d_theta(x - hx, y)) / (2. * hx);
+        let gy = (d_theta(x, y + hy) - d_theta(x, y - hy)) / (2. * hy);
+
+        let det = fx * gy - fy * gx;
+        if det == 0. || !det.is_finite() {
+            return None;
+        }
+
+        let dx = (value * gy - fy * g) / det;
+        let dy = (fx * g - value * gx) / det;
+
+        x -= dx;
+        y -= dy;
+
+        if !x.is_finite() || !y.is_finite() {
+            return None;
+        }
+
+        if converged(dx, x) && converged(dy, y) {
+            return Some(vec2d(x, y));
+        }
+    }
+
+    None
+}
+
+///
+/// Envelope of the family of implicit curves `F(x, y, θ) = 0`.
+///
+/// `guess` must be close to the envelope point of the first `θ`. Values of `θ` for which the
+/// solver does not converge are skipped.
+///
+pub fn envelope<F, I>(f: F, thetas: I, guess: Vector2D) -> Vec<Vector2D>
+where
+    F: Fn(Dual, Dual, Dual) -> Dual,
+    I: IntoIterator<Item = f64>,
+{
+    let mut previous: Option<Vector2D> = None;
+    let mut guess = guess;
+
+    thetas
+        .into_iter()
+        .filter_map(|theta| {
+            let point = solve_implicit(&f, theta, guess)?;
+            guess = match previous {
+                None => point,
+                Some(previous) => point * 2. - previous,
+            };
+            previous = Some(point);
+            Some(point)
+        })
+        .collect()
+}
+
+///
+/// Envelope of the family of parametric curves `θ ↦ (t ↦ (x(t, θ), y(t, θ)))`.
+///
+/// For each `θ` we search the `t` for which the curve is tangent to the envelope, meaning
+/// `∂c/∂t` and `∂c/∂θ` are colinear. `guess` is the value of `t` for the first `θ`.
+///
+pub fn family_envelope<C, I>(curve: C, thetas: I, guess: f64) -> Vec<Vector2D>
+where
+    C: Fn(Dual, Dual) -> (Dual, Dual),
+    I: IntoIterator<Item = f64>,
+{
+    let cross = |t: f64, theta: f64| {
+        let (xt, yt) = curve(Dual::variable(t), theta.into());
+        let (xth, yth) = curve(t.into(), Dual::variable(theta));
+
+        xt.derivative * yth.derivative - yt.derivative * xth.derivative
+    };
+
+    let solve = |theta: f64, mut t: f64| {
+        for _ in 0..MAX_ITERATIONS {
+            let h = step_size(t);
+            let value = cross(t, theta);
+            let derivative = (cross(t + h, theta) - cross(t - h, theta)) / (2. * h);
+
+            if derivative == 0. || !derivative.is_finite() {
+                return None;
+            }
+
+            let dt = value / derivative;
+            t -= dt;
+
+            if !t.is_finite() {
+                return None;
+            }
+
+            if converged(dt, t) {
+                return Some(t);
+            }
+        }
+
+        None
+    };
+
+    let mut previous: Option<f64> = None;
+    let mut guess = guess;
+
+    thetas
+        .into_iter()
+        .filter_map(|theta| {
+            let t = solve(theta, guess)?;
+            guess = match previous {
+                None => t,
+                Some(previous) => 2. * t - previous,
+            };
+            previous = Some(t);
+
+            let (x, y) = curve(t.into(), theta.into());
+            Some(vec2d(x.value, y.value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::linspace;
+
+    #[test]
+    fn safety_parabola() {
+        let (v0, g) = (10., 9.81);
+        let trajectory = |x: Dual, y: Dual, th: Dual| {
+            y - th.tan() * x + g * x * x / (2. * v0 * v0 * th.cos().powi(2))
+        };
+
+        let points = envelope(trajectory, linspace(0.8, 1.4, 50), vec2d(v0 * v0 / g, 0.));
+        assert_eq!(points.len(), 51);
+        for p in points {
+            let expected = v0 * v0 / (2. * g) - g * p.x() * p.x() / (2. * v0 * v0);
+            assert!((p.y() - expected).abs() < 1e-6, "{p:?}");
+        }
+    }
+
+    #[test]
+    fn tangents_of_a_circle() {
+        // The tangent to the unit circle at angle θ, its envelope is the circle
+        let tangent = |t: Dual, th: Dual| (th.cos() - t * th.sin(), th.sin() + t * th.cos());
+
+        let points = family_envelope(tangent, linspace(0., 3., 30), 0.1);
+        assert_eq!(points.len(), 31);
+        for p in points {
+            assert!((p.norm() - 1.).abs() < 1e-9, "{p:?}");
+        }
+    }
+}
@@ -0,0 +1,485 @@
+//! Control points that can be dragged on the canvas.
+//!
+//! The application describes its handles from its current state, at their world positions.
+//! [`Handles::update`] is called from `canvas::Program::update` with a [`HandleState`] as the
+//! program state, and returns the new position of the handle being dragged, for the
+//! application to turn into a message.
+
+use crate::{conic::Line, hit::Shape, vec2d, Plotter, Vector2D};
+use iced_graphics::{
+    widget::canvas::{
+        event::{Event, Status},
+        Cursor, Fill, Frame, Stroke, Style,
+    },
+    Color, Point, Rectangle,
+};
+use iced_native::{mouse, touch};
+
+/// Radius in pixels of a drawn handle
+const RADIUS: f64 = 6.;
+/// Distance in pixels under which a handle can be grabbed
+const GRAB_DISTANCE: f64 = 10.;
+
+/// Where a handle is allowed to go
+pub enum Constraint {
+    Free,
+    /// Anywhere on an infinite line, for example an axis
+    Line(Line),
+    Segment(Vector2D, Vector2D),
+    Circle {
+        center: Vector2D,
+        radius: f64,
+    },
+    /// On the parametric curve `t -> curve(t)` for `t` in `[start, end]`
+    Curve {
+        curve: Box<dyn Fn(f64) -> Vector2D>,
+        start: f64,
+        end: f64,
+    },
+    /// Inside an axis aligned rectangle
+    Rectangle {
+        min: Vector2D,
+        max: Vector2D,
+    },
+    /// Inside a disk
+    Disk {
+        center: Vector2D,
+        radius: f64,
+    },
+}
+
+impl Constraint {
+    pub fn curve<F>(curve: F, start: f64, end: f64) -> Self
+    where
+        F: Fn(f64) -> Vector2D + 'static,
+    {
+        Self::Curve {
+            curve: Box::new(curve),
+            start,
+            end,
+        }
+    }
+
+    /// The allowed position nearest to `p`
+    pub fn project(&self, p: Vector2D) -> Vector2D {
+        match self {
+            Constraint::Free => p,
+            Constraint::Line(line) => {
+                line.point + line.direction * (p - line.point).dot(line.direction)
+            }
+            &Constraint::Segment(a, b) => {
+                let ab = b - a;
+                let length = ab.dot(ab);
+                if length == 0. {
+                    a
+                } else {
+                    a + ab * ((p - a).dot(ab) / length).clamp(0., 1.)
+                }
+            }
+            &Constraint::Circle { center, radius } => {
+                let v = p - center;
+                if v.norm() == 0. {
+                    center + vec2d(radius, 0.)
+                } else {
+                    center + v.normalize() * radius
+                }
+            }
+            Constraint::Curve { curve, start, end } => project_on_curve(curve, *start, *end, p),
+            &Constraint::Rectangle { min, max } => {
+                vec2d(p.x().clamp(min.x(), max.x()), p.y().clamp(min.y(), max.y()))
+            }
+            &Constraint::Disk { center, radius } => {
+                let v = p - center;
+                if v.norm() <= radius {
+                    p
+                } else {
+                    center + v.normalize() * radius
+                }
+            }
+        }
+    }
+}
+
+/// Coarse sampling of the curve, refined with a ternary search around the nearest sample
+fn project_on_curve(
+    curve: &dyn Fn(f64) -> Vector2D,
+    start: f64,
+    end: f64,
+    p: Vector2D,
+) -> Vector2D {
+    const SAMPLES: usize = 200;
+
+    let distance = |t: f64| (curve(t) - p).norm();
+    let step = (end - start) / SAMPLES as f64;
+
+    let nearest = (0..=SAMPLES)
+        .map(|i| start + i as f64 * step)
+        .min_by(|&a, &b| distance(a).total_cmp(&distance(b)))
+        .unwrap();
+
+    let (mut lo, mut hi) = (
+        (nearest - step).max(start.min(end)),
+        (nearest + step).min(start.max(end)),
+    );
+    for _ in 0..40 {
+        let m1 = lo + (hi - lo) / 3.;
+        let m2 = hi - (hi - lo) / 3.;
+        if distance(m1) < distance(m2) {
+            hi = m2;
+        } else {
+            lo = m1;
+        }
+    }
+
+    curve((lo + hi) / 2.)
+}
+
+pub struct Handle<Id> {
+    pub id: Id,
+    pub position: Vector2D,
+    pub constraint: Constraint,
+    pub color: Color,
+    /// The view stays as it was when the handle was grabbed, while it is dragged
+    pub keeps_view: bool,
+}
+
+impl<Id> Handle<Id> {
+    pub fn new<C: Into<Vector2D>>(id: Id, position: C) -> Self {
+        Self {
+            id,
+            position: position.into(),
+            constraint: Constraint::Free,
+            color: Color::from_rgb(0., 0., 1.),
+            keeps_view: false,
+        }
+    }
+
+    pub fn constrained(self, constraint: Constraint) -> Self {
+        Self { constraint, ..self }
+    }
+
+    pub fn with_color(self, color: Color) -> Self {
+        Self { color, ..self }
+    }
+
+    /// Keeps the view while the handle is dragged, when the view fits what the handle changes
+    pub fn keeping_view(self) -> Self {
+        Self {
+            keeps_view: true,
+            ..self
+        }
+    }
+}
+
+/// Hover and drag state, to be used as (or stored in) the `canvas::Program::State`
+pub struct HandleState<Id> {
+    hovered: Option<Id>,
+    dragged: Option<Id>,
+}
+
+impl<Id> Default for HandleState<Id> {
+    fn default() -> Self {
+        Self {
+            hovered: None,
+            dragged: None,
+        }
+    }
+}
+
+impl<Id> HandleState<Id> {
+    pub fn is_dragging(&self) -> bool {
+        self.dragged.is_some()
+    }
+
+    /// A handle is hovered or dragged
+    pub fn is_active(&self) -> bool {
+        self.hovered.is_some() || self.dragged.is_some()
+    }
+}
+
+/// A handle was moved to `position`, already constrained
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Drag<Id> {
+    pub id: Id,
+    pub position: Vector2D,
+}
+
+pub struct Handles<Id> {
+    handles: Vec<Handle<Id>>,
+}
+
+impl<Id> Default for Handles<Id> {
+    fn default() -> Self {
+        Self {
+            handles: Vec::new(),
+        }
+    }
+}
+
+impl<Id: Clone + PartialEq> Handles<Id> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, handle: Handle<Id>) {
+        self.handles.push(handle);
+    }
+
+    pub fn with(mut self, handle: Handle<Id>) -> Self {
+        self.push(handle);
+        self
+    }
+
+    /// A handle keeping the view is dragged, see [`Handle::keeping_view`]
+    pub fn keeps_view(&self, state: &HandleState<Id>) -> bool {
+        state
+            .dragged
+            .as_ref()
+            .is_some_and(|id| self.handles.iter().any(|h| &h.id == id && h.keeps_view))
+    }
+
+    /// The handle that can be grabbed at `cursor`, the last one wins when they overlap
+    fn grabbable(&self, plotter: &Plotter, cursor: Point) -> Option<&Handle<Id>> {
+        let p = plotter.world_coord(cursor);
+
+        self.handles
+            .iter()
+            .rev()
+            .map(|h| (h, Shape::Point(h.position).distance(p) * plotter.scale()))
+            .filter(|&(_, d)| d <= GRAB_DISTANCE)
+            .fold(None, |best: Option<(&Handle<Id>, f64)>, cur| match best {
+                Some(best) if best.1 <= cur.1 => Some(best),
+                _ => Some(cur),
+            })
+            .map(|(h, _)| h)
+    }
+
+    fn drag_to(
+        &self,
+        state: &HandleState<Id>,
+        plotter: &Plotter,
+        cursor: Point,
+    ) -> Option<Drag<Id>> {
+        let id = state.dragged.as_ref()?;
+        let handle = self.handles.iter().find(|h| &h.id == id)?;
+
+        Some(Drag {
+            id: id.clone(),
+            position: handle.constraint.project(plotter.world_coord(cursor)),
+        })
+    }
+
+    ///
+    /// Handles the mouse and touch events: a press on a handle starts dragging it, and each
+    /// movement while dragging returns its new position.
+    ///
+    pub fn update(
+        &self,
+        state: &mut HandleState<Id>,
+        plotter: &Plotter,
+        event: Event,
+        bounds: Rectangle,
+        cursor: Cursor,
+    ) -> (Status, Option<Drag<Id>>) {
+        let position = match event {
+            Event::Touch(
+                touch::Event::FingerPressed { position, .. }
+                | touch::Event::FingerMoved { position, .. }
+                | touch::Event::FingerLifted { position, .. }
+                | touch::Event::FingerLost { position, .. },
+            ) => Some(Point::new(position.x - bounds.x, position.y - bounds.y)),
+            _ => cursor.position_in(&bounds),
+        };
+
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
+            | Event::Touch(touch::Event::FingerPressed { .. }) => {
+                let grabbed = position.and_then(|p| self.grabbable(plotter, p));
+                match grabbed {
+                    Some(handle) => {
+                        state.dragged = Some(handle.id.clone());
+                        state.hovered = Some(handle.id.clone());
+                        (Status::Captured, None)
+                    }
+                    None => (Status::Ignored, None),
+                }
+            }
+            Event::Mouse(mouse::Event::CursorMoved { .. })
+            | Event::Touch(touch::Event::FingerMoved { .. }) => {
+                if state.dragged.is_some() {
+                    // Dragging outside of the canvas keeps going, clamped to its bounds
+                    let cursor = position.unwrap_or_else(|| match cursor {
+                        Cursor::Available(p) => Point::new(
+                            (p.x - bounds.x).clamp(0., bounds.width),
+                            (p.y - bounds.y).clamp(0., bounds.height),
+                        ),
+                        Cursor::Unavailable => Point::ORIGIN,
+                    });
+                    (Status::Captured, self.drag_to(state, plotter, cursor))
+                } else {
+                    state.hovered = position
+                        .and_then(|p| self.grabbable(plotter, p))
+                        .map(|h| h.id.clone());
+                    (Status::Ignored, None)
+                }
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
+            | Event::Touch(touch::Event::FingerLifted { .. } | touch::Event::FingerLost { .. }) => {
+                if state.dragged.take().is_some() {
+                    (Status::Captured, None)
+                } else {
+                    (Status::Ignored, None)
+                }
+            }
+            _ => (Status::Ignored, None),
+        }
+    }
+
+    /// Draws the handles, the hovered and dragged ones are highlighted
+    pub fn draw(&self, frame: &mut Frame, plotter: &Plotter, state: &HandleState<Id>) {
+        for handle in &self.handles {
+            let active = state.dragged.as_ref() == Some(&handle.id)
+                || (state.dragged.is_none() && state.hovered.as_ref() == Some(&handle.id));
+
+            let radius = if active { RADIUS * 1.5 } else { RADIUS } / plotter.scale();
+            let circle = plotter.circle(handle.position.x(), handle.position.y(), radius);
+
+            let fill = if active { handle.color } else { Color::WHITE };
+            frame.fill(
+                &circle,
+                Fill {
+                    style: Style::Solid(fill),
+                    ..Default::default()
+                },
+            );
+            frame.stroke(
+                &circle,
+                Stroke::default().with_width(2.).with_color(handle.color),
+            );
+        }
+    }
+
+    pub fn mouse_interaction(&self, state: &HandleState<Id>) -> mouse::Interaction {
+        if state.dragged.is_some() {
+            mouse::Interaction::Grabbing
+        } else if state.hovered.is_some() {
+            mouse::Interaction::Grab
+        } else {
+            mouse::Interaction::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iced_graphics::Size;
+
+    fn assert_near(a: Vector2D, b: Vector2D) {
+        assert!((a - b).norm() < 1e-6, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn projections() {
+        let p = vec2d(3., 4.);
+        assert_near(Constraint::Free.project(p), p);
+
+        let diagonal = Constraint::Line(Line::new(vec2d(0., 0.), vec2d(2., 2.)));
+        assert_near(diagonal.project(p), vec2d(3.5, 3.5));
+
+        // Clamped to the end points
+        let segment = Constraint::Segment(vec2d(0., 0.), vec2d(2., 0.));
+        assert_near(segment.project(vec2d(1., 5.)), vec2d(1., 0.));
+        assert_near(segment.project(vec2d(-1., 1.)), vec2d(0., 0.));
+        assert_near(segment.project(vec2d(7., -1.)), vec2d(2., 0.));
+        let point = Constraint::Segment(p, p);
+        assert_near(point.project(vec2d(0., 0.)), p);
+
+        let center = vec2d(1., 1.);
+        let circle = Constraint::Circle { center, radius: 2. };
+        assert_near(circle.project(vec2d(1., 5.)), vec2d(1., 3.));
+        assert_near(circle.project(vec2d(1.5, 1.)), vec2d(3., 1.));
+        // From the center all the points of the circle are as near, one of them is picked
+        assert!(((circle.project(center) - center).norm() - 2.).abs() < 1e-12);
+
+        let rectangle = Constraint::Rectangle {
+            min: vec2d(0., 0.),
+            max: vec2d(2., 1.),
+        };
+        assert_near(rectangle.project(vec2d(1., 0.5)), vec2d(1., 0.5));
+        assert_near(rectangle.project(vec2d(1., 5.)), vec2d(1., 1.));
+        assert_near(rectangle.project(vec2d(-3., -3.)), vec2d(0., 0.));
+
+        let disk = Constraint::Disk { center, radius: 2. };
+        assert_near(disk.project(vec2d(2., 2.)), vec2d(2., 2.));
+        assert_near(disk.project(vec2d(5., 1.)), vec2d(3., 1.));
+
+        // The upper half of the unit circle, the projection is along the radius
+        let arc = Constraint::curve(|t| vec2d(t.cos(), t.sin()), 0., std::f64::consts::PI);
+        let expected = vec2d(3., 4.) / 5.;
+        assert_near(arc.project(p), expected);
+        // Past the ends of the curve
+        assert_near(arc.project(vec2d(1., -3.)), vec2d(1., 0.));
+    }
+
+    fn press(handles: &Handles<u8>, state: &mut HandleState<u8>, plotter: &Plotter, p: Point) {
+        let event = Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left));
+        handles.update(state, plotter, event, bounds(), Cursor::Available(p));
+    }
+
+    fn bounds() -> Rectangle {
+        Rectangle::with_size(Size::new(800., 600.))
+    }
+
+    #[test]
+    fn grab_the_nearest_handle() {
+        let plotter = Plotter::new(100, 800., 600., 100.);
+        // 6 pixels apart, both are in reach of a press between them
+        let handles = Handles::new()
+            .with(Handle::new(1, (0., 0.)))
+            .with(Handle::new(2, (0.06, 0.)));
+
+        let mut state = HandleState::default();
+        press(&handles, &mut state, &plotter, Point::new(402., 300.));
+        assert_eq!(state.dragged, Some(1));
+
+        let mut state = HandleState::default();
+        press(&handles, &mut state, &plotter, Point::new(404., 300.));
+        assert_eq!(state.dragged, Some(2));
+
+        // Too far from both
+        let mut state = HandleState::default();
+        press(&handles, &mut state, &plotter, Point::new(400., 320.));
+        assert!(!state.is_dragging());
+    }
+
+    #[test]
+    fn drag_along_the_constraint() {
+        let plotter = Plotter::new(100, 800., 600., 100.);
+        let handles = Handles::new().with(
+            Handle::new(1, (0., 0.))
+                .constrained(Constraint::Segment(vec2d(0., 0.), vec2d(0., 1.)))
+                .keeping_view(),
+        );
+
+        let mut state = HandleState::default();
+        press(&handles, &mut state, &plotter, Point::new(400., 300.));
+        assert!(handles.keeps_view(&state));
+
+        let event = Event::Mouse(mouse::Event::CursorMoved {
+            position: Point::new(450., 250.),
+        });
+        let cursor = Cursor::Available(Point::new(450., 250.));
+        let (status, drag) = handles.update(&mut state, &plotter, event, bounds(), cursor);
+        assert_eq!(status, Status::Captured);
+        let drag = drag.unwrap();
+        assert_eq!(drag.id, 1);
+        assert_near(drag.position, vec2d(0., 0.5));
+
+        let event = Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left));
+        let (status, drag) = handles.update(&mut state, &plotter, event, bounds(), cursor);
+        assert_eq!((status, drag), (Status::Captured, None));
+        assert!(!state.is_dragging());
+        assert!(!handles.keeps_view(&state));
+    }
+}
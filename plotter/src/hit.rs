@@ -0,0 +1,267 @@
+//! Finding what is drawn under the cursor.
+//!
+//! Items register an id and a shape in world coordinates while drawing, and the cursor position
+//! is mapped back to the world with Plotter::world_coord to find the nearest one.
+
+use crate::{simplify::segment_distance, Plotter, Vector2D};
+use iced_graphics::{
+    widget::canvas::{Fill, Frame, Style, Text},
+    Color, Point, Size,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    Point(Vector2D),
+    Segment(Vector2D, Vector2D),
+    Polyline(Vec<Vector2D>),
+    /// Only the outline of the circle
+    Circle {
+        center: Vector2D,
+        radius: f64,
+    },
+    /// A filled circle
+    Disk {
+        center: Vector2D,
+        radius: f64,
+    },
+}
+
+impl Shape {
+    /// Distance from `p` to the shape, in world units
+    pub fn distance(&self, p: Vector2D) -> f64 {
+        match self {
+            &Shape::Point(c) => (p - c).norm(),
+            &Shape::Segment(a, b) => segment_distance(p, a, b),
+            Shape::Polyline(points) => match points.as_slice() {
+                [] => f64::INFINITY,
+                &[c] => (p - c).norm(),
+                points => points
+                    .windows(2)
+                    .map(|w| segment_distance(p, w[0], w[1]))
+                    .fold(f64::INFINITY, f64::min),
+            },
+            &Shape::Circle { center, radius } => ((p - center).norm() - radius).abs(),
+            &Shape::Disk { center, radius } => ((p - center).norm() - radius).max(0.),
+        }
+    }
+}
+
+pub struct Hit<'a, Id> {
+    pub id: &'a Id,
+    /// Distance to the cursor in pixels
+    pub distance: f64,
+}
+
+pub struct HitMap<Id> {
+    items: Vec<(Id, Shape)>,
+}
+
+impl<Id> Default for HitMap<Id> {
+    fn default() -> Self {
+        Self { items: Vec::new() }
+    }
+}
+
+impl<Id> HitMap<Id> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    pub fn insert(&mut self, id: Id, shape: Shape) {
+        self.items.push((id, shape));
+    }
+
+    pub fn point<C: Into<Vector2D>>(&mut self, id: Id, point: C) {
+        self.insert(id, Shape::Point(point.into()))
+    }
+
+    pub fn segment<C: Into<Vector2D>>(&mut self, id: Id, start: C, end: C) {
+        self.insert(id, Shape::Segment(start.into(), end.into()))
+    }
+
+    pub fn polyline<I, C>(&mut self, id: Id, points: I)
+    where
+        C: Into<Vector2D>,
+        I: IntoIterator<Item = C>,
+    {
+        self.insert(
+            id,
+            Shape::Polyline(points.into_iter().map(Into::into).collect()),
+        )
+    }
+
+    pub fn circle<C: Into<Vector2D>>(&mut self, id: Id, center: C, radius: f64) {
+        self.insert(
+            id,
+            Shape::Circle {
+                center: center.into(),
+                radius,
+            },
+        )
+    }
+
+    pub fn disk<C: Into<Vector2D>>(&mut self, id: Id, center: C, radius: f64) {
+        self.insert(
+            id,
+            Shape::Disk {
+                center: center.into(),
+                radius,
+            },
+        )
+    }
+
+    ///
+    /// The item nearest to `cursor` (a position in the frame), if it is at most `max_distance`
+    /// pixels away. Items inserted last are drawn on top, so they win ties.
+    ///
+    pub fn nearest(
+        &self,
+        plotter: &Plotter,
+        cursor: Point,
+        max_distance: f64,
+    ) -> Option<Hit<'_, Id>> {
+        let p = plotter.world_coord(cursor);
+
+        self.items
+            .iter()
+            .rev()
+            .map(|(id, shape)| Hit {
+                id,
+                distance: shape.distance(p) * plotter.scale(),
+            })
+            .filter(|hit| hit.distance <= max_distance)
+            .fold(None, |best: Option<Hit<Id>>, hit| match best {
+                Some(best) if best.distance <= hit.distance => Some(best),
+                _ => Some(hit),
+            })
+    }
+}
+
+/// Draws `content` in a box next to `position`, kept inside the frame
+pub fn tooltip(frame: &mut Frame, position: Point, content: String) {
+    const SIZE: f32 = 16.;
+    const PADDING: f32 = 4.;
+    const OFFSET: f32 = 12.;
+
+    // There is no text measurement available here, this is a rough estimate of the width
+    let width = content.chars().count() as f32 * SIZE * 0.6 + 2. * PADDING;
+    let height = SIZE + 2. * PADDING;
+
+    let mut top_left = Point::new(position.x + OFFSET, position.y + OFFSET);
+    if top_left.x + width > frame.width() {
+        top_left.x = position.x - OFFSET - width;
+    }
+    if top_left.y + height > frame.height() {
+        top_left.y = position.y - OFFSET - height;
+    }
+
+    frame.fill_rectangle(
+        top_left,
+        Size::new(width, height),
+        Fill {
+            style: Style::Solid(Color::from_rgba(1., 1., 0.9, 0.9)),
+            ..Default::default()
+        },
+    );
+    frame.fill_text(Text {
+        content,
+        position: Point::new(top_left.x + PADDING, top_left.y + PADDING),
+        size: SIZE,
+        ..Default::default()
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec2d;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-12, "{a} != {b}");
+    }
+
+    #[test]
+    fn point_segment_and_polyline_distances() {
+        let p = vec2d(3., 4.);
+        assert_close(Shape::Point(vec2d(0., 0.)).distance(p), 5.);
+
+        // Projecting inside the segment, then past each end
+        let segment = Shape::Segment(vec2d(0., 0.), vec2d(10., 0.));
+        assert_close(segment.distance(p), 4.);
+        assert_close(segment.distance(vec2d(-3., 4.)), 5.);
+        assert_close(segment.distance(vec2d(13., -4.)), 5.);
+        assert_close(Shape::Segment(p, p).distance(vec2d(0., 0.)), 5.);
+
+        let polyline = Shape::Polyline(vec![vec2d(0., 0.), vec2d(10., 0.), vec2d(10., 10.)]);
+        assert_close(polyline.distance(vec2d(8., 5.)), 2.);
+        assert_close(polyline.distance(vec2d(5., -1.)), 1.);
+        assert_close(polyline.distance(vec2d(10., 12.)), 2.);
+        assert_close(Shape::Polyline(vec![vec2d(0., 0.)]).distance(p), 5.);
+        assert_eq!(Shape::Polyline(Vec::new()).distance(p), f64::INFINITY);
+
+        let center = vec2d(0., 0.);
+        let circle = Shape::Circle { center, radius: 2. };
+        assert_close(circle.distance(center), 2.);
+        assert_close(circle.distance(p), 3.);
+        let disk = Shape::Disk { center, radius: 2. };
+        assert_close(disk.distance(vec2d(1., 1.)), 0.);
+        assert_close(disk.distance(p), 3.);
+    }
+
+    #[test]
+    fn threshold_in_pixels() {
+        let mut hits = HitMap::new();
+        hits.point("origin", (0., 0.));
+
+        // The origin is at the center of the frame, 5 pixels away is 0.05 at this scale
+        let plotter = Plotter::new(100, 800., 600., 100.);
+        let cursor = Point::new(405., 300.);
+        let hit = hits.nearest(&plotter, cursor, 10.).unwrap();
+        assert_eq!(*hit.id, "origin");
+        assert!((hit.distance - 5.).abs() < 1e-4);
+        assert!(hits.nearest(&plotter, cursor, 4.).is_none());
+
+        // The same world distance is farther in pixels when zoomed in
+        let zoomed = Plotter::new(100, 800., 600., 1000.);
+        let cursor = zoomed.screen_coord(vec2d(0.05, 0.));
+        assert!(hits.nearest(&zoomed, cursor, 10.).is_none());
+        assert!(hits.nearest(&zoomed, cursor, 60.).is_some());
+    }
+
+    #[test]
+    fn nearest_of_overlapping_shapes() {
+        let plotter = Plotter::new(100, 800., 600., 100.);
+        let mut hits = HitMap::new();
+        hits.disk("disk", (0., 0.), 1.);
+        hits.segment("segment", (-1., 0.1), (1., 0.1));
+        hits.point("point", (0.5, 0.));
+
+        // Inside the disk, which is at a distance of 0, and the nearest
+        let cursor = plotter.screen_coord(vec2d(0.2, 0.12));
+        assert_eq!(*hits.nearest(&plotter, cursor, 10.).unwrap().id, "disk");
+
+        // Just outside the disk, it is still closer than the segment
+        let cursor = plotter.screen_coord(vec2d(1.04, 0.));
+        assert_eq!(*hits.nearest(&plotter, cursor, 10.).unwrap().id, "disk");
+
+        let mut hits = HitMap::new();
+        hits.segment("segment", (-1., 0.1), (1., 0.1));
+        hits.point("point", (0.5, 0.));
+        let cursor = plotter.screen_coord(vec2d(0.5, 0.03));
+        assert_eq!(*hits.nearest(&plotter, cursor, 10.).unwrap().id, "point");
+        let cursor = plotter.screen_coord(vec2d(0.5, 0.07));
+        assert_eq!(*hits.nearest(&plotter, cursor, 10.).unwrap().id, "segment");
+
+        // On ties the last inserted, drawn on top, wins
+        hits.point("on top", (0.5, 0.));
+        let cursor = plotter.screen_coord(vec2d(0.5, 0.03));
+        assert_eq!(*hits.nearest(&plotter, cursor, 10.).unwrap().id, "on top");
+
+        hits.clear();
+        assert!(hits.nearest(&plotter, cursor, 10.).is_none());
+    }
+}
@@ -1,3 +1,40 @@
+pub mod animation;
+pub mod app;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cli;
+pub mod conic;
+pub mod dual;
+pub mod envelope;
+pub mod handle;
+pub mod hit;
+pub mod ode;
+pub mod params;
+pub mod persist;
+pub mod projection;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod raster;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod record;
+pub mod simplify;
+pub mod spline;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod svg;
+pub mod sweep;
+
+pub use animation::Clock;
+pub use app::{run, Visualization};
+pub use conic::Conic;
+pub use dual::Dual;
+pub use handle::{Handle, Handles};
+pub use hit::HitMap;
+pub use params::Parameters;
+pub use projection::Camera;
+pub use simplify::LodPath;
+pub use sweep::Sweeper;
+
+#[doc(hidden)]
+pub use paste;
+
 use iced_graphics::{
     alignment::{Horizontal, Vertical},
     widget::canvas::{
@@ -16,6 +53,7 @@ pub fn eccentricity_to_radius(e: f64) -> (f64, f64) {
     (a, 1.)
 }
 
+#[derive(Clone)]
 pub struct Plotter {
     resolution: usize,
     width: f64,
@@ -23,13 +61,21 @@ pub struct Plotter {
     scale: f64,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Vector2D {
     x: f64,
     y: f64,
 }
 
 impl Vector2D {
+    pub fn x(&self) -> f64 {
+        self.x
+    }
+
+    pub fn y(&self) -> f64 {
+        self.y
+    }
+
     pub fn norm(&self) -> f64 {
         (self.x * self.x + self.y * self.y).sqrt()
     }
@@ -38,6 +84,15 @@ impl Vector2D {
         *self / self.norm()
     }
 
+    pub fn dot(&self, other: Vector2D) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// The vector rotated by a quarter turn counter-clockwise
+    pub fn perp(&self) -> Vector2D {
+        vec2d(-self.y, self.x)
+    }
+
     pub fn rot(&self, angle: f64) -> Vector2D {
         Vector2D {
             x: self.x * angle.cos() - self.y * angle.sin(),
@@ -165,13 +220,38 @@ impl Plotter {
         }
     }
 
-    fn screen_coord(&self, mut v: Vector2D) -> Point {
+    /// Position in the frame of a point in world coordinates
+    pub fn screen_coord(&self, mut v: Vector2D) -> Point {
         v.y *= -1.;
         v += vec2d(self.width / 2., self.height / 2.);
         v *= self.scale;
         v.into()
     }
 
+    /// Point in world coordinates at a position in the frame, the inverse of Plotter::screen_coord
+    pub fn world_coord(&self, p: Point) -> Vector2D {
+        let mut v = vec2d(p.x as f64, p.y as f64);
+        v /= self.scale;
+        v -= vec2d(self.width / 2., self.height / 2.);
+        v.y *= -1.;
+        v
+    }
+
+    /// Number of pixels for one world unit
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Visible width in world units, centered on the origin
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+
+    /// Visible height in world units, centered on the origin
+    pub fn height(&self) -> f64 {
+        self.height
+    }
+
     pub fn axis(&self) -> Path2D {
         Path2D::new(|axis| {
             axis.move_to(self.screen_coord(vec2d(0., -self.height / 2.)));
@@ -274,17 +354,110 @@ impl Plotter {
     }
 
     pub fn ellipse(&self, x: f64, y: f64, a: f64, b: f64) -> Path2D {
+        self.rotated_ellipse(x, y, a, b, 0.)
+    }
+
+    ///
+    /// Ellipse whose `a` axis makes an angle of `rotation` with the x axis, counter-clockwise.
+    ///
+    pub fn rotated_ellipse(&self, x: f64, y: f64, a: f64, b: f64, rotation: f64) -> Path2D {
+        self.elliptical_arc((x, y), a, b, rotation, 0., std::f64::consts::TAU)
+    }
+
+    /// The arc in screen orientation, as `screen_coord` flips the y axis the angles are reversed
+    fn screen_arc(
+        &self,
+        center: Vector2D,
+        (a, b): (f64, f64),
+        rotation: f64,
+        start: f64,
+        end: f64,
+    ) -> Elliptical {
+        Elliptical {
+            center: self.screen_coord(center),
+            radii: Vector::new((a * self.scale) as _, (b * self.scale) as _),
+            rotation: -rotation as f32,
+            start_angle: -start as f32,
+            end_angle: -end as f32,
+        }
+    }
+
+    ///
+    /// Part of a rotated ellipse, going counter-clockwise from `start` to `end`.
+    ///
+    /// The angles are the parameter of the ellipse, measured from its `a` axis: the point at
+    /// angle `t` is `a*cos(t)` along the `a` axis and `b*sin(t)` along the `b` axis.
+    ///
+    pub fn elliptical_arc<C>(
+        &self,
+        center: C,
+        a: f64,
+        b: f64,
+        rotation: f64,
+        start: f64,
+        end: f64,
+    ) -> Path2D
+    where
+        C: Into<Vector2D>,
+    {
+        let arc = self.screen_arc(center.into(), (a, b), rotation, start, end);
+        Path2D::new(|builder| builder.ellipse(arc))
+    }
+
+    /// Closed path going from the center along the Plotter::elliptical_arc, meant to be filled
+    pub fn elliptical_sector<C>(
+        &self,
+        center: C,
+        a: f64,
+        b: f64,
+        rotation: f64,
+        start: f64,
+        end: f64,
+    ) -> Path2D
+    where
+        C: Into<Vector2D>,
+    {
+        let center = center.into();
+        let arc = self.screen_arc(center, (a, b), rotation, start, end);
+
         Path2D::new(|builder| {
-            builder.ellipse(Elliptical {
-                center: self.screen_coord(vec2d(x, y)),
-                radii: Vector::new((a * self.scale) as _, (b * self.scale) as _),
-                rotation: 0.,
-                start_angle: 0.,
-                end_angle: std::f32::consts::TAU,
-            })
+            builder.ellipse(arc);
+            builder.line_to(self.screen_coord(center));
+            builder.close();
         })
     }
 
+    /// Circular arc going counter-clockwise from `start` to `end`, angles are from the x axis
+    pub fn arc(&self, x: f64, y: f64, radius: f64, start: f64, end: f64) -> Path2D {
+        self.elliptical_arc((x, y), radius, radius, 0., start, end)
+    }
+
+    /// Pie slice of the Plotter::arc, meant to be filled
+    pub fn sector(&self, x: f64, y: f64, radius: f64, start: f64, end: f64) -> Path2D {
+        self.elliptical_sector((x, y), radius, radius, 0., start, end)
+    }
+
+    ///
+    /// Marks the angle at `vertex` going counter-clockwise from the direction `from` to the
+    /// direction `to`.
+    ///
+    pub fn angle_marker<C>(&self, vertex: C, from: C, to: C, radius: f64) -> Path2D
+    where
+        C: Into<Vector2D>,
+    {
+        let vertex = vertex.into();
+        let from = from.into();
+        let to = to.into();
+
+        let start = from.y.atan2(from.x);
+        let mut end = to.y.atan2(to.x);
+        if end < start {
+            end += std::f64::consts::TAU;
+        }
+
+        self.arc(vertex.x, vertex.y, radius, start, end)
+    }
+
     pub fn centered_ellipse(&self, a: f64, b: f64) -> Path2D {
         self.ellipse(0., 0., a, b)
     }
@@ -304,21 +477,42 @@ impl Plotter {
         }
     }
 
-    /// `y = a*x^2 + b*x + c`
+    /// `y = a*x^2 + b*x + c`, clipped to the viewport
     pub fn parabola(&self, a: f64, b: f64, c: f64) -> Path2D {
-        let edge = if a < 0. { self.height } else { -self.height } / 2.;
+        let conic = Conic::parabola(a, b, c);
+        match conic.kind() {
+            // Without the square term it is the line y = b*x + c
+            conic::ConicKind::Degenerate => self.line(conic::Line::new(vec2d(0., c), vec2d(1., b))),
+            _ => self.conic(&conic),
+        }
+    }
+
+    ///
+    /// Segment of the tangent to the graph of `f` at `x`, centered on the curve.
+    /// The derivative is computed exactly using dual numbers.
+    ///
+    pub fn tangent<F>(&self, f: F, x: f64, length: f64) -> Path2D
+    where
+        F: Fn(Dual) -> Dual,
+    {
+        let point = vec2d(x, f(Dual::constant(x)).value);
+        let t = dual::tangent(f, x) * (length / 2.);
 
-        let delta = b * b - 4. * a * (c + edge);
-        assert!(
-            delta >= 0.,
-            "Why should delta be negative, there must be an intersection"
-        );
+        self.path([point - t, point + t])
+    }
 
-        let x1 = (-b + delta.sqrt()) / (2. * a);
-        let x2 = (-b - delta.sqrt()) / (2. * a);
-        let (x1, x2) = if x1 < x2 { (x1, x2) } else { (x2, x1) };
+    ///
+    /// Segment of the normal to the graph of `f` at `x`, starting on the curve and going
+    /// to its left (counter-clockwise from the tangent).
+    ///
+    pub fn normal<F>(&self, f: F, x: f64, length: f64) -> Path2D
+    where
+        F: Fn(Dual) -> Dual,
+    {
+        let point = vec2d(x, f(Dual::constant(x)).value);
+        let n = dual::normal(f, x) * length;
 
-        self.function(x1, x2, |x| a * x * x + b * x + c)
+        self.path([point, point + n])
     }
 
     pub fn function<F>(&self, start: f64, end: f64, f: F) -> Path2D
@@ -0,0 +1,94 @@
+//! Numerical integration of ordinary differential equations.
+//!
+//! The systems are written in first order form `y' = f(t, y)`, with the state in an array: a
+//! second order equation on a position becomes a system on the position and the speed. They
+//! are integrated with the classic fourth order Runge-Kutta method, at a fixed step.
+
+fn add<const N: usize>(y: [f64; N], k: [f64; N], factor: f64) -> [f64; N] {
+    let mut result = y;
+    for (r, k) in result.iter_mut().zip(k) {
+        *r += k * factor;
+    }
+    result
+}
+
+/// State after a step of `dt` from `y` at time `t`
+pub fn rk4_step<F, const N: usize>(f: &F, t: f64, y: [f64; N], dt: f64) -> [f64; N]
+where
+    F: Fn(f64, [f64; N]) -> [f64; N],
+{
+    let k1 = f(t, y);
+    let k2 = f(t + dt / 2., add(y, k1, dt / 2.));
+    let k3 = f(t + dt / 2., add(y, k2, dt / 2.));
+    let k4 = f(t + dt, add(y, k3, dt));
+
+    let mut next = y;
+    for (i, n) in next.iter_mut().enumerate() {
+        *n += dt / 6. * (k1[i] + 2. * k2[i] + 2. * k3[i] + k4[i]);
+    }
+    next
+}
+
+///
+/// Times and states of the solution starting at `y0` at time `t0`, one per step of `dt`. The
+/// initial state comes first and the iterator never ends, it is up to the caller to stop.
+///
+pub fn integrate<F, const N: usize>(
+    f: F,
+    t0: f64,
+    y0: [f64; N],
+    dt: f64,
+) -> impl Iterator<Item = (f64, [f64; N])>
+where
+    F: Fn(f64, [f64; N]) -> [f64; N],
+{
+    std::iter::successors(Some((t0, y0)), move |&(t, y)| {
+        Some((t + dt, rk4_step(&f, t, y, dt)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    /// State of `y' = f(t, y)` at `t`, integrated from 0 in `steps` steps
+    fn solve<F, const N: usize>(f: F, y0: [f64; N], t: f64, steps: usize) -> [f64; N]
+    where
+        F: Fn(f64, [f64; N]) -> [f64; N],
+    {
+        let (_, y) = integrate(f, 0., y0, t / steps as f64).nth(steps).unwrap();
+        y
+    }
+
+    #[test]
+    fn exponential_decay() {
+        let [y] = solve(|_, [y]| [-y], [1.], 1., 100);
+        assert!((y - (-1f64).exp()).abs() < 1e-9);
+
+        // Depending on the time too, y = exp(t²)
+        let [y] = solve(|t, [y]| [2. * t * y], [1.], 1., 100);
+        assert!((y - 1f64.exp()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn harmonic_oscillator_fourth_order() {
+        // x'' = -x from x = 1 at rest, back to the start after a period
+        let error = |steps| {
+            let [x, v] = solve(|_, [x, v]| [v, -x], [1., 0.], TAU, steps);
+            ((x - 1.).powi(2) + v * v).sqrt()
+        };
+        assert!(error(100) < 1e-5);
+
+        // Halving the step divides the error by 2⁴
+        let ratio = error(100) / error(200);
+        assert!((ratio - 16.).abs() < 1., "{ratio}");
+    }
+
+    #[test]
+    fn starts_with_the_initial_state() {
+        let mut solution = integrate(|_, [y]| [y], 2., [3.], 0.5);
+        assert_eq!(solution.next(), Some((2., [3.])));
+        assert_eq!(solution.next().map(|(t, _)| t), Some(2.5));
+    }
+}
@@ -0,0 +1,707 @@
+//! Declarative parameters.
+//!
+//! The [`parameters!`](crate::parameters) macro describes the parameters of an application
+//! once: their field, default value and [`Spec`]. It generates the struct holding them and an
+//! enum naming them, from which [`panel`] builds the controls. Every change goes through
+//! [`Parameters::set`], so values are always rounded and clamped the same way.
+//!
+//! A post can also name some interesting sets of parameters as [`Preset`]s, picked with
+//! [`preset_list`].
+
+use iced_native::{
+    alignment,
+    overlay::menu,
+    widget::{checkbox, column, container, pick_list, row, scrollable, slider, text},
+    Element, Length,
+};
+use std::{fmt, ops::RangeInclusive};
+
+/// Width of the labels, so that the sliders are aligned
+const LABEL_WIDTH: f32 = 220.;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Kind {
+    Float {
+        range: RangeInclusive<f64>,
+    },
+    Int {
+        range: RangeInclusive<i64>,
+    },
+    Toggle,
+    /// One of the options, stored as its index
+    Choice(Vec<String>),
+}
+
+/// How a parameter is shown and edited
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spec {
+    pub label: &'static str,
+    pub kind: Kind,
+    /// Step of the slider, in shown units
+    pub step: f64,
+    /// Decimals shown for floats
+    pub precision: usize,
+    pub unit: &'static str,
+    /// Factor from the stored value to the shown one, e.g. to show an angle in turns. Links and
+    /// the command line use shown values too.
+    pub scale: f64,
+}
+
+impl Spec {
+    fn new(label: &'static str, kind: Kind) -> Self {
+        Self {
+            label,
+            kind,
+            step: 1.,
+            precision: 2,
+            unit: "",
+            scale: 1.,
+        }
+    }
+
+    pub fn float(label: &'static str, range: RangeInclusive<f64>) -> Self {
+        Self {
+            step: (range.end() - range.start()) / 100.,
+            ..Self::new(label, Kind::Float { range })
+        }
+    }
+
+    pub fn int(label: &'static str, range: RangeInclusive<i64>) -> Self {
+        Self::new(label, Kind::Int { range })
+    }
+
+    pub fn toggle(label: &'static str) -> Self {
+        Self::new(label, Kind::Toggle)
+    }
+
+    pub fn choice<T: Choice>(label: &'static str) -> Self {
+        Self::new(
+            label,
+            Kind::Choice(T::ALL.iter().map(ToString::to_string).collect()),
+        )
+    }
+
+    pub fn step(self, step: f64) -> Self {
+        Self { step, ..self }
+    }
+
+    pub fn precision(self, precision: usize) -> Self {
+        Self { precision, ..self }
+    }
+
+    pub fn unit(self, unit: &'static str) -> Self {
+        Self { unit, ..self }
+    }
+
+    pub fn scale(self, scale: f64) -> Self {
+        Self { scale, ..self }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        matches!(self.kind, Kind::Float { .. } | Kind::Int { .. })
+    }
+
+    /// Range of the stored values, toggles and choices go over 0 and 1 or the option indices
+    pub fn range(&self) -> RangeInclusive<f64> {
+        match &self.kind {
+            Kind::Float { range } => range.clone(),
+            Kind::Int { range } => *range.start() as f64..=*range.end() as f64,
+            Kind::Toggle => 0.0..=1.0,
+            Kind::Choice(options) => 0.0..=(options.len().max(1) - 1) as f64,
+        }
+    }
+
+    ///
+    /// Converts `value` to the kind of the parameter, rounding it to the steps of the range.
+    /// Values that are not finite are rejected, they would go through the clamping.
+    ///
+    pub fn normalize(&self, value: Value) -> Option<Value> {
+        let v = value.as_f64();
+        if !v.is_finite() {
+            return None;
+        }
+
+        let range = self.range();
+        let value = match &self.kind {
+            Kind::Float { .. } => Value::Float(v.clamp(*range.start(), *range.end())),
+            Kind::Int { .. } => {
+                let step = (self.step / self.scale).abs().max(1.);
+                let stepped = range.start() + ((v - range.start()) / step).round() * step;
+                Value::Int(stepped.clamp(*range.start(), *range.end()) as i64)
+            }
+            Kind::Toggle => Value::Bool(v != 0.),
+            Kind::Choice(_) => Value::Choice(v.round().clamp(*range.start(), *range.end()) as _),
+        };
+        Some(value)
+    }
+
+    /// Text of a value in links and on the command line, numbers being in shown units
+    pub fn encode(&self, value: Value) -> String {
+        match (&self.kind, value) {
+            (Kind::Choice(options), Value::Choice(i)) => slug(&options[i]),
+            (Kind::Float { .. } | Kind::Int { .. }, v) => (v.as_f64() * self.scale).to_string(),
+            (_, Value::Float(f)) => f.to_string(),
+            (_, Value::Int(i)) => i.to_string(),
+            (_, Value::Bool(b)) => b.to_string(),
+            (_, Value::Choice(i)) => i.to_string(),
+        }
+    }
+
+    /// A number in shown units, if it is in the range of the parameter
+    fn parse_number(&self, text: &str) -> Option<f64> {
+        let shown: f64 = text.parse().ok()?;
+        let v = shown / self.scale;
+        let range = self.range();
+        // The conversion from shown units may land just outside of the bounds
+        let slack = 1e-9 * (range.end() - range.start()).abs().max(1.);
+        (v >= range.start() - slack && v <= range.end() + slack).then_some(v)
+    }
+
+    ///
+    /// Parses a value encoded by [`encode`](Spec::encode), normalized. Numbers outside of the
+    /// range are rejected rather than clamped, and `NaN` and the infinities are not in any range.
+    ///
+    pub fn decode(&self, text: &str) -> Option<Value> {
+        let value = match &self.kind {
+            Kind::Float { .. } | Kind::Int { .. } => Value::Float(self.parse_number(text)?),
+            Kind::Toggle => Value::Bool(match text {
+                "true" | "1" => true,
+                "false" | "0" => false,
+                _ => return None,
+            }),
+            Kind::Choice(options) => Value::Choice(options.iter().position(|o| slug(o) == text)?),
+        };
+
+        self.normalize(value)
+    }
+
+    /// Label followed by the value in shown units
+    pub fn format(&self, value: Value) -> String {
+        match (&self.kind, value) {
+            (Kind::Float { .. }, v) => format!(
+                "{} ({:.*}{})",
+                self.label,
+                self.precision,
+                v.as_f64() * self.scale,
+                self.unit
+            ),
+            (Kind::Int { .. }, v) => format!(
+                "{} ({}{})",
+                self.label,
+                (v.as_f64() * self.scale).round(),
+                self.unit
+            ),
+            (Kind::Choice(options), Value::Choice(i)) => {
+                format!("{} ({})", self.label, options[i])
+            }
+            _ => self.label.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    Float(f64),
+    Int(i64),
+    Bool(bool),
+    Choice(usize),
+}
+
+impl Value {
+    pub fn as_f64(self) -> f64 {
+        match self {
+            Value::Float(f) => f,
+            Value::Int(i) => i as f64,
+            Value::Bool(b) => b as u8 as f64,
+            Value::Choice(i) => i as f64,
+        }
+    }
+}
+
+/// Types that can be the field of a parameter
+pub trait ParameterValue: Copy {
+    fn into_value(self) -> Value;
+    fn from_value(value: Value) -> Self;
+}
+
+impl ParameterValue for f64 {
+    fn into_value(self) -> Value {
+        Value::Float(self)
+    }
+
+    fn from_value(value: Value) -> Self {
+        value.as_f64()
+    }
+}
+
+macro_rules! int_value {
+    ($($int:ty),*) => {
+        $(
+        impl ParameterValue for $int {
+            fn into_value(self) -> Value {
+                Value::Int(self as i64)
+            }
+
+            fn from_value(value: Value) -> Self {
+                value.as_f64().round() as _
+            }
+        }
+        )*
+    };
+}
+
+int_value!(i32, i64, u32, u64, usize);
+
+impl ParameterValue for bool {
+    fn into_value(self) -> Value {
+        Value::Bool(self)
+    }
+
+    fn from_value(value: Value) -> Self {
+        value.as_f64() != 0.
+    }
+}
+
+/// Enums picked from a list
+pub trait Choice: Copy + PartialEq + fmt::Display + 'static {
+    const ALL: &'static [Self];
+}
+
+impl<T: Choice> ParameterValue for T {
+    fn into_value(self) -> Value {
+        Value::Choice(T::ALL.iter().position(|&c| c == self).unwrap_or(0))
+    }
+
+    fn from_value(value: Value) -> Self {
+        let index = value.as_f64().round().max(0.) as usize;
+        T::ALL[index.min(T::ALL.len() - 1)]
+    }
+}
+
+/// Implemented by [`parameters!`](crate::parameters)
+pub trait Parameters {
+    /// Names a parameter, displayed as the field name with spaces
+    type Key: Copy + Eq + Send + fmt::Debug + fmt::Display + 'static;
+
+    const KEYS: &'static [Self::Key];
+
+    /// The spec can depend on the other parameters, like a range bounded by another value
+    fn spec(&self, key: Self::Key) -> Spec;
+
+    fn get(&self, key: Self::Key) -> Value;
+
+    /// Stores `value` without normalizing it, prefer [`set`](Parameters::set)
+    fn set_raw(&mut self, key: Self::Key, value: Value);
+
+    ///
+    /// Sets a parameter, then keeps the others in their ranges as they may depend on it. A value
+    /// that is not a finite number is ignored.
+    ///
+    fn set(&mut self, key: Self::Key, value: Value) {
+        let Some(value) = self.spec(key).normalize(value) else {
+            return;
+        };
+        self.set_raw(key, value);
+
+        for &k in Self::KEYS {
+            let value = self.get(k);
+            match self.spec(k).normalize(value) {
+                Some(normalized) if normalized != value => self.set_raw(k, normalized),
+                _ => (),
+            }
+        }
+    }
+
+    fn range(&self, key: Self::Key) -> RangeInclusive<f64> {
+        self.spec(key).range()
+    }
+
+    /// The numeric parameters, the ones that can be swept
+    fn numeric(&self) -> Vec<Self::Key> {
+        Self::KEYS
+            .iter()
+            .copied()
+            .filter(|&k| self.spec(k).is_numeric())
+            .collect()
+    }
+
+    /// Encodes the parameters that differ from their default, like `modulo=300&multiplier=59`
+    fn to_query(&self) -> String
+    where
+        Self: Default,
+    {
+        let default = Self::default();
+        Self::KEYS
+            .iter()
+            .filter(|&&k| self.get(k) != default.get(k))
+            .map(|&k| {
+                format!(
+                    "{}={}",
+                    slug(&k.to_string()),
+                    self.spec(k).encode(self.get(k))
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+
+    ///
+    /// Sets the parameters found in a query made by [`to_query`](Parameters::to_query). They are
+    /// set in the order of the keys, as a range can depend on the parameters before it. The
+    /// entries that could not be used are returned as an error, after setting the others.
+    ///
+    fn set_query(&mut self, query: &str) -> Result<(), String> {
+        let mut invalid = Vec::new();
+        let mut entries = Vec::new();
+        for entry in query.split('&').filter(|e| !e.is_empty()) {
+            match entry.split_once('=') {
+                Some((name, value)) => entries.push((entry, name, value)),
+                None => invalid.push(entry),
+            }
+        }
+
+        let names: Vec<_> = Self::KEYS.iter().map(|k| slug(&k.to_string())).collect();
+        for (&key, name) in Self::KEYS.iter().zip(&names) {
+            for &(entry, _, value) in entries.iter().filter(|(_, n, _)| n == name) {
+                match self.spec(key).decode(value) {
+                    Some(value) => self.set(key, value),
+                    None => invalid.push(entry),
+                }
+            }
+        }
+        invalid.extend(
+            entries
+                .iter()
+                .filter(|(_, n, _)| !names.iter().any(|name| name == n))
+                .map(|&(entry, _, _)| entry),
+        );
+
+        if invalid.is_empty() {
+            Ok(())
+        } else {
+            Err(invalid.join(", "))
+        }
+    }
+}
+
+/// Named parameters, for the configurations worth a look
+#[derive(Clone, Debug, PartialEq)]
+pub struct Preset<P> {
+    pub name: &'static str,
+    pub params: P,
+}
+
+impl<P> Preset<P> {
+    pub fn new(name: &'static str, params: P) -> Self {
+        Self { name, params }
+    }
+}
+
+/// Name without spaces, for links and the command line
+pub fn slug(name: &str) -> String {
+    name.replace(' ', "-")
+}
+
+/// Query of a link made with [`to_query`](Parameters::to_query), accepting the query alone too
+pub fn query_of(link: &str) -> &str {
+    let query = link.split_once('?').map_or(link, |(_, query)| query);
+    query.split('#').next().unwrap_or_default()
+}
+
+///
+/// Sliders for the numeric parameters and pick lists for the choices, one per row, then the
+/// toggles on a single row.
+///
+pub fn panel<'a, P, Message, Renderer>(
+    parameters: &P,
+    on_change: fn(P::Key, Value) -> Message,
+) -> Element<'a, Message, Renderer>
+where
+    P: Parameters,
+    Message: Clone + 'a,
+    Renderer: iced_native::text::Renderer + 'a,
+    Renderer::Theme: slider::StyleSheet
+        + text::StyleSheet
+        + checkbox::StyleSheet
+        + pick_list::StyleSheet
+        + scrollable::StyleSheet
+        + menu::StyleSheet
+        + container::StyleSheet,
+    <Renderer::Theme as menu::StyleSheet>::Style:
+        From<<Renderer::Theme as pick_list::StyleSheet>::Style>,
+{
+    let mut rows = Vec::new();
+    let mut toggles = Vec::new();
+
+    for &key in P::KEYS {
+        let spec = parameters.spec(key);
+        let value = parameters.get(key);
+        let label = text(spec.format(value))
+            .width(Length::Fixed(LABEL_WIDTH))
+            .vertical_alignment(alignment::Vertical::Center);
+
+        match &spec.kind {
+            Kind::Float { .. } | Kind::Int { .. } => {
+                let range = spec.range();
+                let scale = spec.scale;
+                let control = slider(
+                    range.start() * scale..=range.end() * scale,
+                    value.as_f64() * scale,
+                    move |v| on_change(key, Value::Float(v / scale)),
+                )
+                .step(spec.step);
+                rows.push(row(vec![label.into(), control.into()]).spacing(10).into());
+            }
+            Kind::Choice(options) => {
+                let selected = match value {
+                    Value::Choice(i) => options.get(i).cloned(),
+                    _ => None,
+                };
+                let positions = options.clone();
+                let control = pick_list(options.clone(), selected, move |o| {
+                    let index = positions.iter().position(|p| *p == o).unwrap_or(0);
+                    on_change(key, Value::Choice(index))
+                });
+                rows.push(row(vec![label.into(), control.into()]).spacing(10).into());
+            }
+            Kind::Toggle => toggles.push(
+                checkbox(spec.label, value.as_f64() != 0., move |b| {
+                    on_change(key, Value::Bool(b))
+                })
+                .into(),
+            ),
+        }
+    }
+
+    if !toggles.is_empty() {
+        rows.push(row(toggles).spacing(10).into());
+    }
+
+    column(rows).spacing(2).into()
+}
+
+/// Picks one of the presets, showing the one equal to the current parameters if any
+pub fn preset_list<'a, P, Message, Renderer>(
+    presets: &[Preset<P>],
+    current: &P,
+    on_select: fn(usize) -> Message,
+) -> Element<'a, Message, Renderer>
+where
+    P: PartialEq,
+    Message: Clone + 'a,
+    Renderer: iced_native::text::Renderer + 'a,
+    Renderer::Theme: text::StyleSheet
+        + pick_list::StyleSheet
+        + scrollable::StyleSheet
+        + menu::StyleSheet
+        + container::StyleSheet,
+    <Renderer::Theme as menu::StyleSheet>::Style:
+        From<<Renderer::Theme as pick_list::StyleSheet>::Style>,
+{
+    let names: Vec<_> = presets.iter().map(|p| p.name).collect();
+    let selected = presets
+        .iter()
+        .find(|p| p.params == *current)
+        .map(|p| p.name);
+
+    let label = text("Preset")
+        .width(Length::Fixed(LABEL_WIDTH))
+        .vertical_alignment(alignment::Vertical::Center);
+    let control = pick_list(names.clone(), selected, move |name| {
+        on_select(names.iter().position(|&n| n == name).unwrap_or(0))
+    })
+    .placeholder("Custom");
+
+    row(vec![label.into(), control.into()]).spacing(10).into()
+}
+
+///
+/// Declares the parameters of an application: a struct with a field per parameter, and an enum
+/// naming them. The identifier between bars can be used in the specs to refer to the current
+/// parameters.
+///
+/// ```ignore
+/// parameters! {
+///     struct Params: Param |params| {
+///         modulo: u64 = 10 => Spec::int("Modulo", 3..=300),
+///         multiplier: u64 = 2 => Spec::int("Multiplier", 2..=params.modulo as i64 - 1),
+///         labels: bool = true => Spec::toggle("Labels on the nodes"),
+///     }
+/// }
+/// ```
+///
+#[macro_export]
+macro_rules! parameters {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident: $key:ident |$params:ident| {
+            $(
+                $(#[$field_meta:meta])*
+                $field:ident: $ty:ty = $default:expr => $spec:expr
+            ),* $(,)?
+        }
+    ) => {
+        $crate::paste::paste! {
+            $(#[$meta])*
+            #[derive(Clone, Debug, PartialEq)]
+            $vis struct $name {
+                $(
+                    $(#[$field_meta])*
+                    pub $field: $ty,
+                )*
+            }
+
+            #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+            $vis enum $key {
+                $([<$field:camel>],)*
+            }
+
+            impl std::fmt::Display for $key {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    let name = match self {
+                        $($key::[<$field:camel>] => stringify!($field),)*
+                    };
+                    write!(f, "{}", name.replace('_', " "))
+                }
+            }
+
+            impl Default for $name {
+                fn default() -> Self {
+                    Self {
+                        $($field: $default,)*
+                    }
+                }
+            }
+
+            impl $crate::params::Parameters for $name {
+                type Key = $key;
+
+                const KEYS: &'static [$key] = &[$($key::[<$field:camel>],)*];
+
+                #[allow(unused_variables)]
+                fn spec(&self, key: $key) -> $crate::params::Spec {
+                    let $params = self;
+                    match key {
+                        $($key::[<$field:camel>] => $spec,)*
+                    }
+                }
+
+                fn get(&self, key: $key) -> $crate::params::Value {
+                    match key {
+                        $($key::[<$field:camel>] => {
+                            $crate::params::ParameterValue::into_value(self.$field)
+                        })*
+                    }
+                }
+
+                fn set_raw(&mut self, key: $key, value: $crate::params::Value) {
+                    match key {
+                        $($key::[<$field:camel>] => {
+                            self.$field = $crate::params::ParameterValue::from_value(value)
+                        })*
+                    }
+                }
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum Shape {
+        Circle,
+        Square,
+    }
+
+    impl fmt::Display for Shape {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let s = match self {
+                Shape::Circle => "round circle",
+                Shape::Square => "square",
+            };
+            write!(f, "{s}")
+        }
+    }
+
+    impl Choice for Shape {
+        const ALL: &'static [Self] = &[Shape::Circle, Shape::Square];
+    }
+
+    crate::parameters! {
+        struct Params: Param |params| {
+            modulo: u64 = 10 => Spec::int("Modulo", 3..=300),
+            multiplier: u64 = 2 => Spec::int("Multiplier", 2..=params.modulo as i64 - 1),
+            angle: f64 = 0.5 => Spec::float("Angle", 0.0..=3.0),
+            shape: Shape = Shape::Circle => Spec::choice::<Shape>("Shape"),
+            labels: bool = true => Spec::toggle("Labels"),
+        }
+    }
+
+    #[test]
+    fn query_round_trip() {
+        let params = Params {
+            modulo: 300,
+            multiplier: 259,
+            angle: 0.1,
+            shape: Shape::Square,
+            labels: false,
+        };
+        let query = params.to_query();
+
+        let mut decoded = Params::default();
+        assert_eq!(decoded.set_query(&query), Ok(()));
+        assert_eq!(decoded, params);
+        assert_eq!(Params::default().to_query(), "");
+    }
+
+    #[test]
+    fn query_in_key_order() {
+        // The multiplier is only in range once the modulo is set
+        let mut params = Params::default();
+        assert_eq!(params.set_query("multiplier=59&modulo=300"), Ok(()));
+        assert_eq!((params.modulo, params.multiplier), (300, 59));
+    }
+
+    #[test]
+    fn invalid_queries() {
+        let mut params = Params::default();
+        let result = params.set_query("modulo=abc&angle=1&color=red&shape=round-circle");
+        assert_eq!(result, Err("modulo=abc, color=red".to_owned()));
+        assert_eq!(params.angle, 1.);
+    }
+
+    #[test]
+    fn shown_units() {
+        let spec = Spec::float("Slope", -1.0..=1.0).scale(180. / std::f64::consts::PI);
+        let Some(Value::Float(radians)) = spec.decode("30") else {
+            panic!("30° is in range");
+        };
+        assert!((radians - std::f64::consts::FRAC_PI_6).abs() < 1e-12);
+        assert_eq!(
+            spec.decode(&spec.encode(Value::Float(1.))),
+            Some(Value::Float(1.))
+        );
+
+        // Past the bounds, rather than clamped
+        assert_eq!(spec.decode("60"), None);
+        assert_eq!(spec.decode("1"), Some(Value::Float(1. / spec.scale)));
+        assert_eq!(Spec::int("Count", 3..=300).decode("301"), None);
+    }
+
+    #[test]
+    fn non_finite_numbers() {
+        let spec = Spec::float("Angle", 0.0..=3.0);
+        for text in ["NaN", "inf", "-inf", "infinity"] {
+            assert_eq!(spec.decode(text), None);
+        }
+
+        let mut params = Params::default();
+        assert!(params.set_query("angle=NaN").is_err());
+        params.set(Param::Angle, Value::Float(f64::NAN));
+        params.set(Param::Modulo, Value::Float(f64::INFINITY));
+        assert_eq!(params, Params::default());
+    }
+}
@@ -0,0 +1,209 @@
+//! Keeping the parameters between sessions.
+//!
+//! The parameters are saved as the query of a shared link, in the local storage on the web and
+//! in `$XDG_CONFIG_HOME/viz` natively. As in the links, the parameters that no longer exist or
+//! are no longer valid are dropped when loading, the others are kept. Everything is dropped when
+//! the version of the saved parameters is not the current one.
+
+use crate::{params::slug, Parameters};
+
+/// Where the parameters of a visualization are saved
+pub struct Store {
+    name: String,
+    version: u32,
+    /// The directory of the saved parameters, `None` when there is no configuration directory
+    #[cfg(not(target_arch = "wasm32"))]
+    dir: Option<std::path::PathBuf>,
+}
+
+/// `$XDG_CONFIG_HOME`, or `~/.config` when it is not set
+#[cfg(not(target_arch = "wasm32"))]
+fn config_dir() -> Option<std::path::PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|c| !c.is_empty())
+        .map(Into::into)
+        .or_else(|| Some(std::path::PathBuf::from(std::env::var_os("HOME")?).join(".config")))
+}
+
+impl Store {
+    /// `name` identifies the visualization, `version` is bumped to drop the saved parameters
+    pub fn new(name: &str, version: u32) -> Self {
+        Self {
+            name: slug(&name.to_lowercase()),
+            version,
+            #[cfg(not(target_arch = "wasm32"))]
+            dir: config_dir().map(|config| config.join("viz")),
+        }
+    }
+
+    /// The saved parameters, the others being left to their default
+    pub fn params<P: Parameters + Default>(&self) -> P {
+        let mut params = P::default();
+        if let Some(query) = self.load() {
+            // Like in a link, the parameters that are no longer valid are left to their default
+            let _ = params.set_query(&query);
+        }
+        params
+    }
+
+    /// The saved query, if it was saved by the current version
+    pub fn load(&self) -> Option<String> {
+        let saved = self.read()?;
+        let (version, query) = saved.split_once('\n')?;
+        (version.parse() == Ok(self.version)).then(|| query.trim_end().to_owned())
+    }
+
+    pub fn save(&self, query: &str) {
+        self.write(&format!("{}\n{query}\n", self.version));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn path(&self) -> Option<std::path::PathBuf> {
+        Some(self.dir.as_ref()?.join(&self.name))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn read(&self) -> Option<String> {
+        std::fs::read_to_string(self.path()?).ok()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn write(&self, content: &str) {
+        let Some(path) = self.path() else {
+            return;
+        };
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, content));
+        if let Err(e) = result {
+            eprintln!("Could not save the parameters to {}: {e}", path.display());
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn key(&self) -> String {
+        format!("viz/{}", self.name)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn read(&self) -> Option<String> {
+        Self::storage()?.get_item(&self.key()).ok()?
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn write(&self, content: &str) {
+        if let Some(storage) = Self::storage() {
+            let _ = storage.set_item(&self.key(), content);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::Spec;
+    use std::fs;
+
+    crate::parameters! {
+        struct Params: Param |params| {
+            modulo: u64 = 10 => Spec::int("Modulo", 3..=300),
+            multiplier: u64 = 2 => Spec::int("Multiplier", 2..=params.modulo as i64 - 1),
+        }
+    }
+
+    /// A store in a temporary directory of its own
+    fn store(test: &str, version: u32) -> Store {
+        let dir = std::env::temp_dir().join(format!("plotter-{test}-{}", std::process::id()));
+        Store {
+            dir: Some(dir),
+            ..Store::new("Modular Table", version)
+        }
+    }
+
+    fn write(store: &Store, content: &[u8]) {
+        let path = store.path().unwrap();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn clean(store: &Store) {
+        fs::remove_dir_all(store.dir.as_ref().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn round_trip() {
+        let store = store("round-trip", 1);
+        assert_eq!(store.load(), None);
+        assert_eq!(store.params::<Params>(), Params::default());
+
+        store.save("modulo=300&multiplier=59");
+        let path = store.path().unwrap();
+        assert!(path.ends_with("modular-table"));
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            "1\nmodulo=300&multiplier=59\n"
+        );
+        assert_eq!(store.load().as_deref(), Some("modulo=300&multiplier=59"));
+        assert_eq!(
+            store.params::<Params>(),
+            Params {
+                modulo: 300,
+                multiplier: 59
+            }
+        );
+        clean(&store);
+    }
+
+    #[test]
+    fn other_version_dropped() {
+        let old = store("other-version", 1);
+        old.save("modulo=300");
+
+        let new = Store {
+            version: 2,
+            ..store("other-version", 1)
+        };
+        assert_eq!(new.load(), None);
+        assert_eq!(new.params::<Params>(), Params::default());
+        clean(&old);
+    }
+
+    #[test]
+    fn corrupt_files_ignored() {
+        let store = store("corrupt", 1);
+        for content in [&b""[..], b"1", b"version\nmodulo=300", b"\xff\xfe\n\x00"] {
+            write(&store, content);
+            assert_eq!(store.load(), None);
+            assert_eq!(store.params::<Params>(), Params::default());
+        }
+
+        // The saved parameters that are not valid are left to their default
+        write(&store, b"1\nmodulo=abc&multiplier=59\n");
+        assert_eq!(store.params::<Params>(), Params::default());
+        write(&store, b"1\nmodulo=300&removed=3&multiplier=400\n");
+        assert_eq!(
+            store.params::<Params>(),
+            Params {
+                modulo: 300,
+                ..Params::default()
+            }
+        );
+        clean(&store);
+    }
+
+    #[test]
+    fn save_failure_not_fatal() {
+        let store = store("save-failure", 1);
+        // The directory of the saved parameters is taken by a file
+        fs::write(store.dir.as_ref().unwrap(), "").unwrap();
+        store.save("modulo=300");
+        assert_eq!(store.load(), None);
+        fs::remove_file(store.dir.as_ref().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,213 @@
+//! Drawing three dimensional scenes with a [`Plotter`].
+//!
+//! A [`Camera`] turns around a point of the scene while looking at it, and projects the points
+//! of the scene to the world coordinates of the plotter, orthographically or in perspective.
+//! The scene has `z` up, the projected paths are then drawn like any other.
+
+use crate::{params::Choice, vec2d, Plotter, Vector2D};
+use iced_graphics::widget::canvas::{path::Path as Path2D, Text};
+use std::{
+    f64::consts::{FRAC_PI_2, TAU},
+    fmt,
+    ops::{Add, Mul, Sub},
+};
+
+/// Rotation of the camera for a drag of one pixel, in radians
+const ROTATION_PER_PIXEL: f64 = 0.01;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Vector3D {
+    x: f64,
+    y: f64,
+    z: f64,
+}
+
+impl Vector3D {
+    pub fn x(&self) -> f64 {
+        self.x
+    }
+
+    pub fn y(&self) -> f64 {
+        self.y
+    }
+
+    pub fn z(&self) -> f64 {
+        self.z
+    }
+
+    pub fn norm(&self) -> f64 {
+        self.dot(*self).sqrt()
+    }
+
+    pub fn dot(&self, other: Vector3D) -> f64 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+}
+
+impl From<(f64, f64, f64)> for Vector3D {
+    fn from((x, y, z): (f64, f64, f64)) -> Self {
+        vec3d(x, y, z)
+    }
+}
+
+pub fn vec3d(x: f64, y: f64, z: f64) -> Vector3D {
+    Vector3D { x, y, z }
+}
+
+impl Add for Vector3D {
+    type Output = Vector3D;
+
+    fn add(self, rhs: Vector3D) -> Vector3D {
+        vec3d(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+    }
+}
+
+impl Sub for Vector3D {
+    type Output = Vector3D;
+
+    fn sub(self, rhs: Vector3D) -> Vector3D {
+        vec3d(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
+    }
+}
+
+impl Mul<f64> for Vector3D {
+    type Output = Vector3D;
+
+    fn mul(self, rhs: f64) -> Vector3D {
+        vec3d(self.x * rhs, self.y * rhs, self.z * rhs)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Projection {
+    /// Parallel lines stay parallel, the sizes do not depend on the distance
+    Orthographic,
+    /// The nearer, the larger, as seen by an eye
+    Perspective,
+}
+
+impl fmt::Display for Projection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Projection::Orthographic => "orthographic",
+            Projection::Perspective => "perspective",
+        };
+        write!(f, "{s}")
+    }
+}
+
+impl Choice for Projection {
+    const ALL: &'static [Self] = &[Projection::Orthographic, Projection::Perspective];
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    /// Angle around the vertical axis, counter-clockwise from the x axis
+    pub yaw: f64,
+    /// Angle above the horizontal plane
+    pub pitch: f64,
+    /// Distance from the center, only used in perspective
+    pub distance: f64,
+    pub projection: Projection,
+    /// Point of the scene looked at, it is drawn at the origin of the plotter
+    pub center: Vector3D,
+}
+
+impl Camera {
+    pub fn new(yaw: f64, pitch: f64) -> Self {
+        Self {
+            yaw,
+            pitch,
+            distance: 10.,
+            projection: Projection::Orthographic,
+            center: vec3d(0., 0., 0.),
+        }
+    }
+
+    pub fn looking_at<C: Into<Vector3D>>(self, center: C) -> Self {
+        Self {
+            center: center.into(),
+            ..self
+        }
+    }
+
+    /// In perspective from `distance`, or orthographic
+    pub fn with_projection(self, projection: Projection, distance: f64) -> Self {
+        Self {
+            projection,
+            distance,
+            ..self
+        }
+    }
+
+    /// Directions of the right and the top of the view, and towards the camera
+    fn basis(&self) -> (Vector3D, Vector3D, Vector3D) {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+
+        let right = vec3d(-sin_yaw, cos_yaw, 0.);
+        let up = vec3d(-sin_pitch * cos_yaw, -sin_pitch * sin_yaw, cos_pitch);
+        let back = vec3d(cos_pitch * cos_yaw, cos_pitch * sin_yaw, sin_pitch);
+        (right, up, back)
+    }
+
+    /// Where `p` is seen in the plane of the plotter
+    pub fn project<C: Into<Vector3D>>(&self, p: C) -> Vector2D {
+        let p = p.into() - self.center;
+        let (right, up, back) = self.basis();
+        let v = vec2d(p.dot(right), p.dot(up));
+
+        match self.projection {
+            Projection::Orthographic => v,
+            Projection::Perspective => {
+                // The points behind the camera are pushed far away instead of being mirrored
+                let depth = (self.distance - p.dot(back)).max(self.distance * 1e-3);
+                v * (self.distance / depth)
+            }
+        }
+    }
+
+    ///
+    /// Yaw and pitch after dragging the view from `from` to `to`, in world coordinates of the
+    /// plotter. The scene turns with the drag, the pitch stays between the verticals.
+    ///
+    pub fn dragged(&self, plotter: &Plotter, from: Vector2D, to: Vector2D) -> (f64, f64) {
+        let delta = (to - from) * (plotter.scale() * ROTATION_PER_PIXEL);
+        let yaw = (self.yaw - delta.x()).rem_euclid(TAU);
+        let pitch = (self.pitch - delta.y()).clamp(-FRAC_PI_2, FRAC_PI_2);
+        (yaw, pitch)
+    }
+
+    pub fn path<I, C>(&self, plotter: &Plotter, parts: I) -> Path2D
+    where
+        C: Into<Vector3D>,
+        I: IntoIterator<Item = C>,
+    {
+        plotter.path(parts.into_iter().map(|p| self.project(p)))
+    }
+
+    /// The `x`, `y` and `z` axes from the origin of the scene, of length `length`
+    pub fn axes(&self, plotter: &Plotter, length: f64) -> Path2D {
+        Path2D::new(|builder| {
+            let origin = plotter.screen_coord(self.project((0., 0., 0.)));
+            for end in [(length, 0., 0.), (0., length, 0.), (0., 0., length)] {
+                builder.move_to(origin);
+                builder.line_to(plotter.screen_coord(self.project(end)));
+            }
+        })
+    }
+
+    /// Circle of the horizontal plane at height `z`
+    pub fn horizontal_circle(&self, plotter: &Plotter, z: f64, radius: f64) -> Path2D {
+        let points = (0..=plotter.resolution).map(|i| {
+            let angle = TAU * i as f64 / plotter.resolution as f64;
+            (radius * angle.cos(), radius * angle.sin(), z)
+        });
+        self.path(plotter, points)
+    }
+
+    pub fn text<C: Into<Vector3D>>(&self, plotter: &Plotter, p: C, content: String) -> Text {
+        let p = self.project(p);
+        plotter.text(p.x(), p.y(), content)
+    }
+}
@@ -0,0 +1,316 @@
+//! Drawing canvas geometries to an image, without a window or a GPU.
+//!
+//! The frames are tessellated by iced as usual, the triangles are then filled on the CPU with
+//! supersampling for the anti-aliasing. Text is drawn with the fallback font of iced.
+
+use iced_graphics::{
+    alignment::{Horizontal, Vertical},
+    triangle::{ColoredVertex2D, Mesh2D},
+    widget::canvas::{Cursor, Geometry, Program},
+    Color, Primitive, Rectangle, Size, Vector,
+};
+use std::sync::OnceLock;
+
+/// Each pixel is the average of SUPERSAMPLING² samples
+const SUPERSAMPLING: u32 = 3;
+
+/// An image in 8 bit sRGB, with an alpha channel
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// Draws `program` as the canvas would in a window of `size`, with the default light theme
+pub fn draw_program<P, Message>(program: &P, size: Size<u32>) -> Image
+where
+    P: Program<Message>,
+{
+    rasterize(geometries(program, size), size, Color::WHITE)
+}
+
+/// The layers drawn by `program` in a canvas of `size`, without a cursor
+pub fn geometries<P, Message>(program: &P, size: Size<u32>) -> Vec<Geometry>
+where
+    P: Program<Message>,
+{
+    let bounds = Rectangle::new(
+        iced_graphics::Point::ORIGIN,
+        Size::new(size.width as f32, size.height as f32),
+    );
+
+    program.draw(
+        &P::State::default(),
+        &Default::default(),
+        bounds,
+        Cursor::Unavailable,
+    )
+}
+
+/// Draws the geometries on top of each other, in order, on an opaque background
+pub fn rasterize(geometries: Vec<Geometry>, size: Size<u32>, background: Color) -> Image {
+    let mut target = Target::new(size, background);
+
+    for geometry in geometries {
+        target.primitive(geometry.into_primitive(), Vector::new(0., 0.), None);
+    }
+
+    target.resolve()
+}
+
+pub(crate) fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1. / 2.4) - 0.055
+    }
+}
+
+fn font() -> &'static fontdue::Font {
+    static FONT: OnceLock<fontdue::Font> = OnceLock::new();
+
+    FONT.get_or_init(|| {
+        fontdue::Font::from_bytes(iced_graphics::font::FALLBACK, Default::default())
+            .expect("the fallback font of iced is valid")
+    })
+}
+
+/// The supersampled image being drawn, in sRGB
+struct Target {
+    width: usize,
+    height: usize,
+    pixels: Vec<[f32; 3]>,
+}
+
+impl Target {
+    fn new(size: Size<u32>, background: Color) -> Self {
+        let width = (size.width * SUPERSAMPLING) as usize;
+        let height = (size.height * SUPERSAMPLING) as usize;
+
+        Self {
+            width,
+            height,
+            pixels: vec![[background.r, background.g, background.b]; width * height],
+        }
+    }
+
+    fn blend(&mut self, x: usize, y: usize, color: [f32; 3], alpha: f32) {
+        let pixel = &mut self.pixels[y * self.width + x];
+        for (p, c) in pixel.iter_mut().zip(color) {
+            *p = *p * (1. - alpha) + c * alpha;
+        }
+    }
+
+    /// `clip` is in supersampled pixels, as `(x_min, y_min, x_max, y_max)`
+    fn primitive(
+        &mut self,
+        primitive: Primitive,
+        translation: Vector,
+        clip: Option<(f32, f32, f32, f32)>,
+    ) {
+        match primitive {
+            Primitive::Group { primitives } => {
+                // Like the renderers of iced, the text goes on top of the meshes
+                let (text, meshes): (Vec<_>, Vec<_>) = primitives
+                    .into_iter()
+                    .partition(|p| matches!(p, Primitive::Text { .. }));
+
+                for p in meshes.into_iter().chain(text) {
+                    self.primitive(p, translation, clip);
+                }
+            }
+            Primitive::Translate {
+                translation: t,
+                content,
+            } => self.primitive(*content, translation + t, clip),
+            Primitive::Clip { bounds, content } => {
+                let s = SUPERSAMPLING as f32;
+                let (x, y) = (bounds.x + translation.x, bounds.y + translation.y);
+                let mut rect = (
+                    x * s,
+                    y * s,
+                    (x + bounds.width) * s,
+                    (y + bounds.height) * s,
+                );
+                if let Some(c) = clip {
+                    rect = (
+                        rect.0.max(c.0),
+                        rect.1.max(c.1),
+                        rect.2.min(c.2),
+                        rect.3.min(c.3),
+                    );
+                }
+                self.primitive(*content, translation, Some(rect))
+            }
+            Primitive::Cached { cache } => self.primitive((*cache).clone(), translation, clip),
+            Primitive::SolidMesh { buffers, .. } => self.mesh(&buffers, translation, clip),
+            Primitive::Text {
+                content,
+                bounds,
+                color,
+                size,
+                horizontal_alignment,
+                vertical_alignment,
+                ..
+            } => self.text(
+                &content,
+                (bounds.x + translation.x, bounds.y + translation.y),
+                color,
+                size,
+                (horizontal_alignment, vertical_alignment),
+            ),
+            // Not produced by the plots
+            _ => {}
+        }
+    }
+
+    fn clip_bounds(&self, clip: Option<(f32, f32, f32, f32)>) -> (f32, f32, f32, f32) {
+        let full = (0., 0., self.width as f32, self.height as f32);
+        match clip {
+            Some(c) => (c.0.max(0.), c.1.max(0.), c.2.min(full.2), c.3.min(full.3)),
+            None => full,
+        }
+    }
+
+    fn mesh(
+        &mut self,
+        mesh: &Mesh2D<ColoredVertex2D>,
+        translation: Vector,
+        clip: Option<(f32, f32, f32, f32)>,
+    ) {
+        let s = SUPERSAMPLING as f32;
+        let (x_min, y_min, x_max, y_max) = self.clip_bounds(clip);
+
+        let position = |i: u32| {
+            let [x, y] = mesh.vertices[i as usize].position;
+            ((x + translation.x) * s, (y + translation.y) * s)
+        };
+
+        for triangle in mesh.indices.chunks_exact(3) {
+            let (a, b, c) = (
+                position(triangle[0]),
+                position(triangle[1]),
+                position(triangle[2]),
+            );
+
+            let area = (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0);
+            if area == 0. {
+                continue;
+            }
+
+            let [r, g, bl, alpha] = mesh.vertices[triangle[0] as usize].color;
+            let color = [linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(bl)];
+
+            let left = a.0.min(b.0).min(c.0).max(x_min).floor() as usize;
+            let right = a.0.max(b.0).max(c.0).min(x_max).ceil() as usize;
+            let top = a.1.min(b.1).min(c.1).max(y_min).floor() as usize;
+            let bottom = a.1.max(b.1).max(c.1).min(y_max).ceil() as usize;
+
+            // Edge functions, all of the same sign as the area inside the triangle
+            let edge = |p: (f32, f32), q: (f32, f32), x: f32, y: f32| {
+                ((q.0 - p.0) * (y - p.1) - (q.1 - p.1) * (x - p.0)) * area.signum()
+            };
+
+            for y in top..bottom {
+                for x in left..right {
+                    let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                    if edge(a, b, px, py) >= 0.
+                        && edge(b, c, px, py) >= 0.
+                        && edge(c, a, px, py) >= 0.
+                    {
+                        self.blend(x, y, color, alpha);
+                    }
+                }
+            }
+        }
+    }
+
+    fn text(
+        &mut self,
+        content: &str,
+        position: (f32, f32),
+        color: Color,
+        size: f32,
+        (horizontal, vertical): (Horizontal, Vertical),
+    ) {
+        let s = SUPERSAMPLING as f32;
+        let font = font();
+        let px = size * s;
+
+        let width: f32 = content
+            .chars()
+            .map(|c| font.metrics(c, px).advance_width)
+            .sum();
+        let line = font
+            .horizontal_line_metrics(px)
+            .expect("the fallback font is horizontal");
+
+        let mut x = position.0 * s
+            - match horizontal {
+                Horizontal::Left => 0.,
+                Horizontal::Center => width / 2.,
+                Horizontal::Right => width,
+            };
+        let top = position.1 * s
+            - match vertical {
+                Vertical::Top => 0.,
+                Vertical::Center => (line.ascent - line.descent) / 2.,
+                Vertical::Bottom => line.ascent - line.descent,
+            };
+        let baseline = top + line.ascent;
+
+        let rgb = [color.r, color.g, color.b];
+        for c in content.chars() {
+            let (metrics, coverage) = font.rasterize(c, px);
+
+            let left = x.round() as i64 + metrics.xmin as i64;
+            let glyph_top = baseline.round() as i64 - metrics.height as i64 - metrics.ymin as i64;
+
+            for (j, row) in coverage.chunks(metrics.width.max(1)).enumerate() {
+                for (i, &cov) in row.iter().enumerate() {
+                    let (gx, gy) = (left + i as i64, glyph_top + j as i64);
+                    if cov > 0
+                        && (0..self.width as i64).contains(&gx)
+                        && (0..self.height as i64).contains(&gy)
+                    {
+                        self.blend(gx as usize, gy as usize, rgb, color.a * cov as f32 / 255.);
+                    }
+                }
+            }
+
+            x += metrics.advance_width;
+        }
+    }
+
+    /// Averages the samples of each pixel
+    fn resolve(self) -> Image {
+        let s = SUPERSAMPLING as usize;
+        let (width, height) = (self.width / s, self.height / s);
+        let mut pixels = Vec::with_capacity(width * height * 4);
+
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0.; 3];
+                for sy in 0..s {
+                    for sx in 0..s {
+                        let p = self.pixels[(y * s + sy) * self.width + x * s + sx];
+                        for (acc, c) in sum.iter_mut().zip(p) {
+                            *acc += c;
+                        }
+                    }
+                }
+
+                for c in sum {
+                    pixels.push((c / (s * s) as f32 * 255.).round().clamp(0., 255.) as u8);
+                }
+                pixels.push(255);
+            }
+        }
+
+        Image {
+            width: width as u32,
+            height: height as u32,
+            pixels,
+        }
+    }
+}
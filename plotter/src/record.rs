@@ -0,0 +1,426 @@
+//! Recording animations to files, without a window.
+//!
+//! A recording steps the time, and optionally sweeps one parameter, drawing each frame with
+//! the [`raster`](crate::raster) module. The output format comes from the extension: `.gif`
+//! for an animated GIF, `.png` for an animated PNG, anything else is a directory in which a
+//! numbered PNG is written for each frame.
+//!
+//! The options are parsed with the rest of the command line, see [`cli`](crate::cli).
+
+use crate::{
+    params::{slug, Spec},
+    raster::Image,
+    sweep::{Sweep, SweepMode},
+};
+use clap::{value_parser, Arg, ArgMatches};
+use iced_graphics::Size;
+use std::{
+    error::Error,
+    fmt,
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Gif,
+    Apng,
+    PngSequence,
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("gif") => Format::Gif,
+            Some("png") => Format::Apng,
+            _ => Format::PngSequence,
+        }
+    }
+}
+
+pub struct ParameterSweep<P> {
+    pub parameter: P,
+    pub sweep: Sweep,
+    pub mode: SweepMode,
+}
+
+pub struct RecordOptions<P> {
+    pub output: PathBuf,
+    pub format: Format,
+    pub size: Size<u32>,
+    pub fps: u32,
+    pub duration: f64,
+    pub sweep: Option<ParameterSweep<P>>,
+}
+
+/// What to draw in a frame of the recording
+pub struct RecordFrame<P> {
+    pub index: usize,
+    /// Time since the start of the recording, in seconds
+    pub time: f64,
+    /// The swept parameter and its value for this frame
+    pub parameter: Option<(P, f64)>,
+}
+
+/// The options of the recordings, `--size` is shared with the rendering so it is in
+/// [`cli`](crate::cli)
+pub fn args(parameters: &[String]) -> Vec<Arg<'_>> {
+    let option = |name| Arg::new(name).long(name).requires("record");
+
+    vec![
+        Arg::new("record")
+            .long("record")
+            .value_name("PATH")
+            .value_parser(value_parser!(PathBuf))
+            .help("Records an animation to an animated .gif or .png, or a directory of PNGs"),
+        option("fps")
+            .value_name("FPS")
+            .value_parser(value_parser!(u32).range(1..))
+            .default_value("30")
+            .help("Frames per second"),
+        option("duration")
+            .value_name("SECS")
+            .value_parser(value_parser!(f64))
+            .help("Length of the recording [default: 5, or the length of --values]"),
+        option("sweep")
+            .value_name("PARAMETER")
+            .possible_values(parameters.iter().map(String::as_str))
+            .help("Parameter to sweep"),
+        option("from")
+            .value_name("VALUE")
+            .value_parser(value_parser!(f64))
+            .requires("sweep")
+            .help("Start of the sweep [default: start of the parameter range]"),
+        option("to")
+            .value_name("VALUE")
+            .value_parser(value_parser!(f64))
+            .requires("sweep")
+            .help("End of the sweep [default: end of the parameter range]"),
+        option("values")
+            .value_name("LIST")
+            .value_parser(value_parser!(f64))
+            .multiple_values(true)
+            .use_value_delimiter(true)
+            .requires("sweep")
+            .conflicts_with_all(&["from", "to"])
+            .help("Values to step through instead of a range, e.g. 2,21,44"),
+        option("pause")
+            .value_name("SECS")
+            .value_parser(value_parser!(f64))
+            .default_value("1")
+            .help("Time spent on each value of --values"),
+        option("period")
+            .value_name("SECS")
+            .value_parser(value_parser!(f64))
+            .help("Duration of one pass over the range [default: the duration]"),
+        option("mode")
+            .value_name("MODE")
+            .possible_values(SweepMode::ALL.map(SweepMode::name))
+            .default_value("once")
+            .help("How the sweep goes over the range or the values"),
+    ]
+}
+
+impl<P> RecordOptions<P>
+where
+    P: Clone + fmt::Display,
+{
+    ///
+    /// The recording options from the matches of [`args`], `None` when there is no `--record`.
+    /// The parameters are the ones that can be swept, `spec` giving their default range and the
+    /// scale of the values of the command line, which are in shown units.
+    ///
+    pub fn from_matches<F>(
+        matches: &ArgMatches,
+        size: Size<u32>,
+        parameters: &[P],
+        spec: F,
+    ) -> Option<Self>
+    where
+        F: Fn(&P) -> Spec,
+    {
+        let output = matches.get_one::<PathBuf>("record")?.clone();
+        let values: Option<Vec<f64>> = matches
+            .get_many::<f64>("values")
+            .map(|v| v.copied().collect());
+        let pause = *matches.get_one::<f64>("pause")?;
+        let mode = matches.get_one::<String>("mode")?;
+        let mode = SweepMode::ALL.into_iter().find(|m| m.name() == mode)?;
+
+        let duration = match (matches.get_one::<f64>("duration"), &values) {
+            (Some(&d), _) => d,
+            (None, Some(values)) => values.len() as f64 * pause,
+            (None, None) => 5.,
+        };
+
+        let parameter = matches.get_one::<String>("sweep").and_then(|name| {
+            parameters
+                .iter()
+                .find(|p| slug(&p.to_string()) == *name)
+                .cloned()
+        });
+
+        let sweep = parameter.map(|parameter| {
+            let spec = spec(&parameter);
+            let stored = |shown: &f64| shown / spec.scale;
+            let sweep = match values {
+                Some(values) => Sweep::Values {
+                    values: values.iter().map(stored).collect(),
+                    pause,
+                },
+                None => {
+                    let default = spec.range();
+                    let from = matches.get_one::<f64>("from").map(stored);
+                    let to = matches.get_one::<f64>("to").map(stored);
+                    Sweep::Range {
+                        range: from.unwrap_or(*default.start())..=to.unwrap_or(*default.end()),
+                        duration: matches
+                            .get_one::<f64>("period")
+                            .copied()
+                            .unwrap_or(duration),
+                    }
+                }
+            };
+
+            ParameterSweep {
+                parameter,
+                sweep,
+                mode,
+            }
+        });
+
+        Some(Self {
+            format: Format::from_path(&output),
+            output,
+            size,
+            fps: *matches.get_one::<u32>("fps")?,
+            duration,
+            sweep,
+        })
+    }
+
+    pub fn frame_count(&self) -> usize {
+        ((self.duration * self.fps as f64).ceil() as usize).max(1)
+    }
+
+    pub fn frames(&self) -> impl Iterator<Item = RecordFrame<P>> + '_ {
+        (0..self.frame_count()).map(|index| {
+            let time = index as f64 / self.fps as f64;
+            let parameter = self.sweep.as_ref().map(|s| {
+                let (value, _) = s.sweep.value(s.mode, time);
+                (s.parameter.clone(), value)
+            });
+
+            RecordFrame {
+                index,
+                time,
+                parameter,
+            }
+        })
+    }
+
+    /// Draws all the frames with `draw` and writes them to the output
+    pub fn record<F>(&self, mut draw: F) -> Result<(), Box<dyn Error>>
+    where
+        F: FnMut(&RecordFrame<P>) -> Image,
+    {
+        let count = self.frame_count();
+        let (width, height) = (self.size.width, self.size.height);
+
+        match self.format {
+            Format::Gif => {
+                let file = BufWriter::new(File::create(&self.output)?);
+                let mut encoder = gif::Encoder::new(file, width as u16, height as u16, &[])?;
+                encoder.set_repeat(gif::Repeat::Infinite)?;
+
+                // The delays of GIF frames are in hundredths of a second
+                let delay = (100. / self.fps as f64).round() as u16;
+                for frame in self.frames() {
+                    let mut image = draw(&frame);
+                    let mut gif_frame = gif::Frame::from_rgba_speed(
+                        width as u16,
+                        height as u16,
+                        &mut image.pixels,
+                        10,
+                    );
+                    gif_frame.delay = delay;
+                    encoder.write_frame(&gif_frame)?;
+                }
+            }
+            Format::Apng => {
+                let file = BufWriter::new(File::create(&self.output)?);
+                let mut encoder = png::Encoder::new(file, width, height);
+                encoder.set_color(png::ColorType::Rgba);
+                encoder.set_depth(png::BitDepth::Eight);
+                encoder.set_animated(count as u32, 0)?;
+                encoder.set_frame_delay(1, self.fps as u16)?;
+
+                let mut writer = encoder.write_header()?;
+                for frame in self.frames() {
+                    writer.write_image_data(&draw(&frame).pixels)?;
+                }
+                writer.finish()?;
+            }
+            Format::PngSequence => {
+                std::fs::create_dir_all(&self.output)?;
+                for frame in self.frames() {
+                    let path = self.output.join(format!("frame_{:04}.png", frame.index));
+                    write_png(&path, &draw(&frame))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn write_png(path: &Path, image: &Image) -> Result<(), Box<dyn Error>> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, image.width, image.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&image.pixels)?;
+    writer.finish()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Command;
+    use std::{f64::consts::PI, fs, io::BufReader};
+
+    fn options(output: &Path, flags: &[&str]) -> RecordOptions<&'static str> {
+        let parameters = ["angle"];
+        let names = vec![slug(parameters[0])];
+        let matches = Command::new("test").args(args(&names)).get_matches_from(
+            ["test", "--record", output.to_str().unwrap()]
+                .iter()
+                .chain(flags),
+        );
+        let spec = |_: &&str| Spec::float("Angle", 0.0..=PI).scale(180. / PI);
+
+        RecordOptions::from_matches(&matches, Size::new(4, 3), &parameters, spec).unwrap()
+    }
+
+    #[test]
+    fn frame_count_and_duration() {
+        let output = Path::new("out.gif");
+        assert_eq!(options(output, &[]).frame_count(), 150);
+        assert_eq!(
+            options(output, &["--fps", "10", "--duration", "2"]).frame_count(),
+            20
+        );
+        // A started frame is recorded
+        assert_eq!(options(output, &["--duration", "0.01"]).frame_count(), 1);
+        assert_eq!(options(output, &["--duration", "0"]).frame_count(), 1);
+
+        // Long enough to stay on each value
+        let values = options(output, &["--sweep", "angle", "--values", "30,60,90"]);
+        assert_eq!(values.duration, 3.);
+        let values = options(
+            output,
+            &["--sweep", "angle", "--values", "30,60", "--pause", "0.5"],
+        );
+        assert_eq!(values.duration, 1.);
+
+        let times: Vec<_> = options(output, &["--fps", "4", "--duration", "1"])
+            .frames()
+            .map(|frame| frame.time)
+            .collect();
+        assert_eq!(times, [0., 0.25, 0.5, 0.75]);
+    }
+
+    #[test]
+    fn sweep_in_shown_units() {
+        let output = Path::new("out.gif");
+        let range = options(output, &["--sweep", "angle", "--from", "0", "--to", "90"]);
+        let sweep = range.sweep.unwrap();
+        assert_eq!(sweep.parameter, "angle");
+        assert_eq!(sweep.mode, SweepMode::Once);
+        match sweep.sweep {
+            Sweep::Range { range, duration } => {
+                assert_eq!(range, 0.0..=PI / 2.);
+                assert_eq!(duration, 5.);
+            }
+            Sweep::Values { .. } => panic!("expected a range"),
+        }
+
+        // Without bounds, the whole range of the parameter
+        let default = options(output, &["--sweep", "angle", "--period", "2"]);
+        assert_eq!(
+            default.sweep.unwrap().sweep,
+            Sweep::Range {
+                range: 0.0..=PI,
+                duration: 2.
+            }
+        );
+
+        let values = options(output, &["--sweep", "angle", "--values", "180"]);
+        assert_eq!(
+            values.sweep.unwrap().sweep,
+            Sweep::Values {
+                values: vec![PI],
+                pause: 1.
+            }
+        );
+    }
+
+    /// Records 3 frames of 4x3 pixels at 10 frames per second to `output`
+    fn record_frames(output: &Path) -> RecordOptions<&'static str> {
+        let options = options(output, &["--fps", "10", "--duration", "0.3"]);
+        options
+            .record(|frame| Image {
+                width: 4,
+                height: 3,
+                pixels: [frame.index as u8 * 100, 0, 0, 255].repeat(12),
+            })
+            .unwrap();
+        options
+    }
+
+    #[test]
+    fn formats() {
+        let dir = std::env::temp_dir().join(format!("plotter-record-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let gif = dir.join("out.gif");
+        assert_eq!(record_frames(&gif).format, Format::Gif);
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(File::open(&gif).unwrap())
+            .unwrap();
+        let mut delays = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delays.push(frame.delay);
+        }
+        assert_eq!(delays, [10, 10, 10]);
+
+        let apng = dir.join("out.png");
+        assert_eq!(record_frames(&apng).format, Format::Apng);
+        let decoder = png::Decoder::new(BufReader::new(File::open(&apng).unwrap()));
+        let reader = decoder.read_info().unwrap();
+        let info = reader.info();
+        assert_eq!((info.width, info.height), (4, 3));
+        assert_eq!(info.animation_control.unwrap().num_frames, 3);
+        let control = info.frame_control.unwrap();
+        assert_eq!((control.delay_num, control.delay_den), (1, 10));
+
+        let sequence = dir.join("frames");
+        assert_eq!(record_frames(&sequence).format, Format::PngSequence);
+        let mut files: Vec<_> = fs::read_dir(&sequence)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        assert_eq!(
+            files,
+            ["frame_0000.png", "frame_0001.png", "frame_0002.png"]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,332 @@
+//! Polyline simplification and level of detail for dense paths.
+//!
+//! The transform from world to screen coordinates is a uniform scaling (plus a flip and a
+//! translation), so simplifying in world space with a tolerance of `pixels / scale` is the same
+//! as simplifying in screen space.
+
+use crate::{
+    spline::{fit_bezier, CubicBezier},
+    Path2D, Plotter, Vector2D,
+};
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+};
+
+/// Distance from `p` to the segment `[a, b]`
+pub(crate) fn segment_distance(p: Vector2D, a: Vector2D, b: Vector2D) -> f64 {
+    let ab = b - a;
+    let length = ab.dot(ab);
+    if length == 0. {
+        return (p - a).norm();
+    }
+
+    let t = ((p - a).dot(ab) / length).clamp(0., 1.);
+    (p - (a + ab * t)).norm()
+}
+
+///
+/// Ramer–Douglas–Peucker: keeps the points needed for the polyline to stay within `tolerance`
+/// of the original one. The first and last points are always kept.
+///
+pub fn ramer_douglas_peucker(points: &[Vector2D], tolerance: f64) -> Vec<Vector2D> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    // Explicit stack, the recursion can get very deep on large inputs
+    let mut stack = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = stack.pop() {
+        let (a, b) = (points[first], points[last]);
+
+        let (index, distance) = points[first + 1..last]
+            .iter()
+            .enumerate()
+            .map(|(i, &p)| (first + 1 + i, segment_distance(p, a, b)))
+            .fold(
+                (first, 0.),
+                |max, cur| if cur.1 > max.1 { cur } else { max },
+            );
+
+        if distance > tolerance {
+            keep[index] = true;
+            if index - first > 1 {
+                stack.push((first, index));
+            }
+            if last - index > 1 {
+                stack.push((index, last));
+            }
+        }
+    }
+
+    points
+        .iter()
+        .zip(keep)
+        .filter_map(|(&p, k)| k.then_some(p))
+        .collect()
+}
+
+fn triangle_area(a: Vector2D, b: Vector2D, c: Vector2D) -> f64 {
+    let (ab, ac) = (b - a, c - a);
+    (ab.x() * ac.y() - ab.y() * ac.x()).abs() / 2.
+}
+
+#[derive(PartialEq)]
+struct Candidate {
+    area: f64,
+    index: usize,
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    // Reversed, so that the BinaryHeap pops the smallest area first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .area
+            .total_cmp(&self.area)
+            .then(other.index.cmp(&self.index))
+    }
+}
+
+///
+/// Visvalingam–Whyatt: repeatedly removes the point forming the smallest triangle with its
+/// neighbours, until all remaining triangles have an area of at least `min_area`.
+///
+pub fn visvalingam(points: &[Vector2D], min_area: f64) -> Vec<Vector2D> {
+    let n = points.len();
+    if n < 3 {
+        return points.to_vec();
+    }
+
+    let mut previous: Vec<usize> = (0..n).map(|i| i.wrapping_sub(1)).collect();
+    let mut next: Vec<usize> = (1..=n).collect();
+    let mut areas = vec![f64::INFINITY; n];
+    let mut heap = BinaryHeap::with_capacity(n);
+
+    for i in 1..n - 1 {
+        areas[i] = triangle_area(points[i - 1], points[i], points[i + 1]);
+        heap.push(Candidate {
+            area: areas[i],
+            index: i,
+        });
+    }
+
+    let mut removed = vec![false; n];
+    while let Some(Candidate { area, index }) = heap.pop() {
+        // Outdated entry, the area changed when a neighbour was removed
+        if removed[index] || area != areas[index] {
+            continue;
+        }
+        if area >= min_area {
+            break;
+        }
+
+        removed[index] = true;
+        let (p, q) = (previous[index], next[index]);
+        next[p] = q;
+        previous[q] = p;
+
+        for i in [p, q] {
+            if i == 0 || i == n - 1 {
+                continue;
+            }
+            // The area of a point may not drop below the one we just removed, or points would
+            // be removed in the wrong order
+            let new_area = triangle_area(points[previous[i]], points[i], points[next[i]]).max(area);
+            areas[i] = new_area;
+            heap.push(Candidate {
+                area: new_area,
+                index: i,
+            });
+        }
+    }
+
+    points
+        .iter()
+        .zip(removed)
+        .filter_map(|(&p, r)| (!r).then_some(p))
+        .collect()
+}
+
+/// Levels are spaced by a factor of √2 in scale
+fn zoom_level(scale: f64) -> i32 {
+    (scale.log2() * 2.).floor() as i32
+}
+
+/// The largest scale of a zoom level, simplifying for it is precise enough for the whole level
+fn level_scale(level: i32) -> f64 {
+    ((level + 1) as f64 / 2.).exp2()
+}
+
+enum Detail {
+    Polyline(Vec<Vector2D>),
+    Fitted(Vec<CubicBezier>),
+}
+
+///
+/// A dense path that is simplified once per zoom level.
+///
+/// The simplified versions are cached, so redrawing at a known zoom level does not go through
+/// all the points again. They are keyed by the zoom level and the bits of the tolerance.
+///
+pub struct LodPath {
+    points: Vec<Vector2D>,
+    fitted: bool,
+    levels: RefCell<HashMap<(i32, u64), Detail>>,
+}
+
+impl LodPath {
+    /// Simplifies the path with Ramer–Douglas–Peucker, and draws it as a polyline
+    pub fn new<I, C>(points: I) -> Self
+    where
+        C: Into<Vector2D>,
+        I: IntoIterator<Item = C>,
+    {
+        Self {
+            points: points.into_iter().map(Into::into).collect(),
+            fitted: false,
+            levels: Default::default(),
+        }
+    }
+
+    /// Draws the path as Bézier segments fitted on the points, see Plotter::fitted_path
+    pub fn fitted<I, C>(points: I) -> Self
+    where
+        C: Into<Vector2D>,
+        I: IntoIterator<Item = C>,
+    {
+        Self {
+            fitted: true,
+            ..Self::new(points)
+        }
+    }
+
+    pub fn points(&self) -> &[Vector2D] {
+        &self.points
+    }
+
+    /// Path at the zoom level of `plotter`, at most `tolerance` pixels away from the points
+    pub fn path(&self, plotter: &Plotter, tolerance: f64) -> Path2D {
+        let level = zoom_level(plotter.scale);
+        let mut levels = self.levels.borrow_mut();
+
+        let detail = levels
+            .entry((level, tolerance.to_bits()))
+            .or_insert_with(|| {
+                let tolerance = tolerance / level_scale(level);
+                if self.fitted {
+                    // Fitting goes through all the points, so most are dropped first. Each step
+                    // gets half of the tolerance.
+                    let simplified = ramer_douglas_peucker(&self.points, tolerance / 2.);
+                    Detail::Fitted(fit_bezier(&simplified, tolerance / 2.))
+                } else {
+                    Detail::Polyline(ramer_douglas_peucker(&self.points, tolerance))
+                }
+            });
+
+        match detail {
+            Detail::Polyline(points) => plotter.path(points.iter().copied()),
+            Detail::Fitted(segments) => plotter.beziers(segments),
+        }
+    }
+}
+
+impl Plotter {
+    ///
+    /// Same as Plotter::path, dropping the points that are not needed to stay within
+    /// `tolerance` pixels of the original polyline.
+    ///
+    pub fn simplified_path<I, C>(&self, points: I, tolerance: f64) -> Path2D
+    where
+        C: Into<Vector2D>,
+        I: IntoIterator<Item = C>,
+    {
+        let points: Vec<Vector2D> = points.into_iter().map(Into::into).collect();
+        self.path(ramer_douglas_peucker(&points, tolerance / self.scale))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec2d;
+
+    fn spiral() -> Vec<Vector2D> {
+        (0..5_000)
+            .map(|i| {
+                let t = i as f64 * 0.01;
+                vec2d(t.cos(), t.sin()) * (1. + t / 10.)
+            })
+            .collect()
+    }
+
+    /// Distance from `p` to the closest segment of the polyline
+    fn polyline_distance(p: Vector2D, polyline: &[Vector2D]) -> f64 {
+        polyline
+            .windows(2)
+            .map(|w| segment_distance(p, w[0], w[1]))
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    #[test]
+    fn ramer_douglas_peucker_error_bound() {
+        let points = spiral();
+        for tolerance in [1e-1, 1e-2, 1e-3] {
+            let simplified = ramer_douglas_peucker(&points, tolerance);
+            assert!(simplified.len() < points.len());
+            assert_eq!(simplified[0], points[0]);
+            assert_eq!(simplified[simplified.len() - 1], points[points.len() - 1]);
+            for &p in &points {
+                assert!(polyline_distance(p, &simplified) <= tolerance);
+            }
+        }
+    }
+
+    #[test]
+    fn visvalingam_drops_smallest_triangles_first() {
+        // Aligned points form empty triangles
+        let line: Vec<_> = (0..100).map(|i| vec2d(i as f64, 2. * i as f64)).collect();
+        assert_eq!(visvalingam(&line, 1e-9), vec![line[0], line[99]]);
+
+        let points = spiral();
+        let fine = visvalingam(&points, 1e-6);
+        let coarse = visvalingam(&points, 1e-4);
+        assert!(coarse.len() < fine.len() && fine.len() < points.len());
+        for simplified in [&fine, &coarse] {
+            assert_eq!(simplified[0], points[0]);
+            assert_eq!(simplified[simplified.len() - 1], points[points.len() - 1]);
+        }
+        // The points are removed in the same order whatever the threshold
+        assert!(coarse.iter().all(|p| fine.contains(p)));
+    }
+
+    #[test]
+    fn lod_path_cached_per_tolerance() {
+        let path = LodPath::new(spiral());
+        let plotter = Plotter::new(100, 800., 600., 100.);
+
+        path.path(&plotter, 1.);
+        path.path(&plotter, 0.1);
+        path.path(&plotter, 1.);
+
+        let levels = path.levels.borrow();
+        assert_eq!(levels.len(), 2);
+        let length = |tolerance: f64| match &levels[&(zoom_level(100.), tolerance.to_bits())] {
+            Detail::Polyline(points) => points.len(),
+            Detail::Fitted(_) => unreachable!(),
+        };
+        assert!(length(0.1) > length(1.));
+    }
+}
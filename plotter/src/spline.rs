@@ -0,0 +1,464 @@
+//! Smooth curves through points, drawn with native Bézier commands.
+
+use crate::{vec2d, Path2D, Plotter, Vector2D};
+
+/// Newton iterations used to improve the parameterization while fitting
+const MAX_REPARAMETERIZATIONS: usize = 4;
+
+/// A cubic Bézier segment: start, two control points, end
+pub type CubicBezier = [Vector2D; 4];
+
+///
+/// Centripetal Catmull-Rom spline going through all the points, as cubic Bézier segments.
+///
+/// Unlike the uniform variant it never forms cusps or self-intersections inside a segment.
+///
+pub fn catmull_rom(points: &[Vector2D]) -> Vec<CubicBezier> {
+    if points.len() < 2 {
+        return Vec::new();
+    }
+
+    let n = points.len();
+    // Phantom points extend the first and last segments in a straight line
+    let at = |i: isize| -> Vector2D {
+        if i < 0 {
+            points[0] * 2. - points[1]
+        } else if i as usize >= n {
+            points[n - 1] * 2. - points[n - 2]
+        } else {
+            points[i as usize]
+        }
+    };
+
+    (0..n as isize - 1)
+        .map(|i| {
+            let (p0, p1, p2, p3) = (at(i - 1), at(i), at(i + 1), at(i + 2));
+
+            // The knot intervals are the square root of the distances
+            let d1 = (p1 - p0).norm().sqrt();
+            let d2 = (p2 - p1).norm().sqrt();
+            let d3 = (p3 - p2).norm().sqrt();
+
+            let c1 = if d1 == 0. || d2 == 0. {
+                p1 + (p2 - p1) / 3.
+            } else {
+                (p2 * (d1 * d1) - p0 * (d2 * d2) + p1 * (2. * d1 * d1 + 3. * d1 * d2 + d2 * d2))
+                    / (3. * d1 * (d1 + d2))
+            };
+            let c2 = if d3 == 0. || d2 == 0. {
+                p2 - (p2 - p1) / 3.
+            } else {
+                (p1 * (d3 * d3) - p3 * (d2 * d2) + p2 * (2. * d3 * d3 + 3. * d3 * d2 + d2 * d2))
+                    / (3. * d3 * (d3 + d2))
+            };
+
+            [p1, c1, c2, p2]
+        })
+        .collect()
+}
+
+/// Solves a tridiagonal system in place, `diagonal` and `rhs` are overwritten
+fn solve_tridiagonal(lower: &[f64], diagonal: &mut [f64], upper: &[f64], rhs: &mut [f64]) {
+    let n = diagonal.len();
+    for i in 1..n {
+        let w = lower[i - 1] / diagonal[i - 1];
+        diagonal[i] -= w * upper[i - 1];
+        rhs[i] -= w * rhs[i - 1];
+    }
+
+    rhs[n - 1] /= diagonal[n - 1];
+    for i in (0..n - 1).rev() {
+        rhs[i] = (rhs[i] - upper[i] * rhs[i + 1]) / diagonal[i];
+    }
+}
+
+///
+/// Natural cubic spline going through all the points, as cubic Bézier segments.
+///
+/// Each coordinate is interpolated against the chord length, with zero curvature at both ends.
+///
+pub fn natural_cubic(points: &[Vector2D]) -> Vec<CubicBezier> {
+    let n = points.len();
+    if n < 2 {
+        return Vec::new();
+    }
+
+    let h: Vec<f64> = points
+        .windows(2)
+        .map(|w| (w[1] - w[0]).norm().max(f64::EPSILON))
+        .collect();
+
+    // Second derivatives at the knots, zero at both ends
+    let mut second = vec![vec2d(0., 0.); n];
+    if n > 2 {
+        let inner = n - 2;
+        let lower: Vec<f64> = h[1..inner].to_vec();
+        let upper = lower.clone();
+
+        let slope = |i: usize| (points[i + 1] - points[i]) / h[i];
+        for coord in 0..2 {
+            let mut diagonal: Vec<f64> = (0..inner).map(|i| 2. * (h[i] + h[i + 1])).collect();
+            let mut rhs: Vec<f64> = (0..inner)
+                .map(|i| {
+                    let d = (slope(i + 1) - slope(i)) * 6.;
+                    if coord == 0 {
+                        d.x()
+                    } else {
+                        d.y()
+                    }
+                })
+                .collect();
+
+            solve_tridiagonal(&lower, &mut diagonal, &upper, &mut rhs);
+
+            for (i, m) in rhs.into_iter().enumerate() {
+                second[i + 1] = if coord == 0 {
+                    vec2d(m, second[i + 1].y())
+                } else {
+                    vec2d(second[i + 1].x(), m)
+                };
+            }
+        }
+    }
+
+    (0..n - 1)
+        .map(|i| {
+            let (p0, p1) = (points[i], points[i + 1]);
+            let (m0, m1) = (second[i], second[i + 1]);
+            let slope = (p1 - p0) / h[i];
+
+            // First derivatives at both ends of the segment
+            let d0 = slope - (m0 * 2. + m1) * (h[i] / 6.);
+            let d1 = slope + (m0 + m1 * 2.) * (h[i] / 6.);
+
+            [p0, p0 + d0 * (h[i] / 3.), p1 - d1 * (h[i] / 3.), p1]
+        })
+        .collect()
+}
+
+fn bezier_point(b: &CubicBezier, t: f64) -> Vector2D {
+    let s = 1. - t;
+    b[0] * (s * s * s) + b[1] * (3. * s * s * t) + b[2] * (3. * s * t * t) + b[3] * (t * t * t)
+}
+
+fn bezier_derivative(b: &CubicBezier, t: f64) -> Vector2D {
+    let s = 1. - t;
+    (b[1] - b[0]) * (3. * s * s) + (b[2] - b[1]) * (6. * s * t) + (b[3] - b[2]) * (3. * t * t)
+}
+
+fn bezier_second_derivative(b: &CubicBezier, t: f64) -> Vector2D {
+    (b[2] - b[1] * 2. + b[0]) * (6. * (1. - t)) + (b[3] - b[2] * 2. + b[1]) * (6. * t)
+}
+
+fn chord_length_parameterize(points: &[Vector2D]) -> Vec<f64> {
+    let mut u = Vec::with_capacity(points.len());
+    let mut total = 0.;
+    u.push(0.);
+    for w in points.windows(2) {
+        total += (w[1] - w[0]).norm();
+        u.push(total);
+    }
+
+    if total > 0. {
+        u.iter_mut().for_each(|x| *x /= total);
+    }
+    u
+}
+
+/// Least-squares fit of a cubic with the given end tangents
+fn generate_bezier(points: &[Vector2D], u: &[f64], left: Vector2D, right: Vector2D) -> CubicBezier {
+    let first = points[0];
+    let last = points[points.len() - 1];
+
+    let (mut c00, mut c01, mut c11, mut x0, mut x1) = (0., 0., 0., 0., 0.);
+    for (&p, &t) in points.iter().zip(u) {
+        let s = 1. - t;
+        let b0 = s * s * s;
+        let b1 = 3. * s * s * t;
+        let b2 = 3. * s * t * t;
+        let b3 = t * t * t;
+
+        let a0 = left * b1;
+        let a1 = right * b2;
+
+        c00 += a0.dot(a0);
+        c01 += a0.dot(a1);
+        c11 += a1.dot(a1);
+
+        let tmp = p - (first * (b0 + b1) + last * (b2 + b3));
+        x0 += a0.dot(tmp);
+        x1 += a1.dot(tmp);
+    }
+
+    let det = c00 * c11 - c01 * c01;
+    let (alpha_l, alpha_r) = if det.abs() > f64::EPSILON {
+        ((x0 * c11 - x1 * c01) / det, (c00 * x1 - c01 * x0) / det)
+    } else {
+        (0., 0.)
+    };
+
+    // Fall back on a simple heuristic when the fit degenerates
+    let segment = (last - first).norm();
+    let epsilon = 1e-6 * segment;
+    if alpha_l < epsilon || alpha_r < epsilon {
+        let d = segment / 3.;
+        return [first, first + left * d, last + right * d, last];
+    }
+
+    [first, first + left * alpha_l, last + right * alpha_r, last]
+}
+
+/// Maximum squared distance of the points to the curve, and where it happens
+fn max_error(points: &[Vector2D], bezier: &CubicBezier, u: &[f64]) -> (f64, usize) {
+    let mut worst = (0., points.len() / 2);
+    for (i, (&p, &t)) in points.iter().zip(u).enumerate().skip(1) {
+        let d = bezier_point(bezier, t) - p;
+        let dist = d.dot(d);
+        if dist >= worst.0 {
+            worst = (dist, i);
+        }
+    }
+    worst
+}
+
+fn reparameterize(points: &[Vector2D], bezier: &CubicBezier, u: &mut [f64]) {
+    for (&p, t) in points.iter().zip(u.iter_mut()) {
+        let d = bezier_point(bezier, *t) - p;
+        let d1 = bezier_derivative(bezier, *t);
+        let d2 = bezier_second_derivative(bezier, *t);
+
+        let numerator = d.dot(d1);
+        let denominator = d1.dot(d1) + d.dot(d2);
+        if denominator != 0. {
+            *t = (*t - numerator / denominator).clamp(0., 1.);
+        }
+    }
+}
+
+/// Fits a single cubic on the points, or returns where the fit is the worst to split there
+fn fit_cubic(
+    points: &[Vector2D],
+    left: Vector2D,
+    right: Vector2D,
+    tolerance: f64,
+) -> Result<CubicBezier, usize> {
+    let first = points[0];
+    let last = points[points.len() - 1];
+
+    if points.len() == 2 {
+        let d = (last - first).norm() / 3.;
+        return Ok([first, first + left * d, last + right * d, last]);
+    }
+
+    let squared_tolerance = tolerance * tolerance;
+
+    let mut u = chord_length_parameterize(points);
+    let mut bezier = generate_bezier(points, &u, left, right);
+    let (mut error, mut split) = max_error(points, &bezier, &u);
+
+    if error < squared_tolerance {
+        return Ok(bezier);
+    }
+
+    // Close enough to try improving the parameterization instead of splitting
+    if error < 4. * squared_tolerance {
+        for _ in 0..MAX_REPARAMETERIZATIONS {
+            reparameterize(points, &bezier, &mut u);
+            bezier = generate_bezier(points, &u, left, right);
+            (error, split) = max_error(points, &bezier, &u);
+
+            if error < squared_tolerance {
+                return Ok(bezier);
+            }
+        }
+    }
+
+    Err(split.clamp(1, points.len() - 2))
+}
+
+///
+/// Approximates a polyline with as few cubic Bézier segments as possible, every point of the
+/// polyline being at most `tolerance` away from the curve.
+///
+pub fn fit_bezier(points: &[Vector2D], tolerance: f64) -> Vec<CubicBezier> {
+    // Repeated points carry no information and break the tangent estimations
+    let mut points = points.to_vec();
+    points.dedup();
+
+    if points.len() < 2 {
+        return Vec::new();
+    }
+
+    let n = points.len();
+    let left = (points[1] - points[0]).normalize();
+    let right = (points[n - 2] - points[n - 1]).normalize();
+
+    // Explicit stack, the splits can get very deep on noisy inputs. The second half of a split
+    // is pushed first, for the segments to come out in order.
+    let mut out = Vec::new();
+    let mut stack = vec![(0, n - 1, left, right)];
+    while let Some((first, last, left, right)) = stack.pop() {
+        let part = &points[first..=last];
+        match fit_cubic(part, left, right, tolerance) {
+            Ok(bezier) => out.push(bezier),
+            Err(split) => {
+                let center = part[split - 1] - part[split + 1];
+                let center = if center.norm() > 0. {
+                    center.normalize()
+                } else {
+                    (part[split - 1] - part[split]).perp().normalize()
+                };
+
+                let split = first + split;
+                stack.push((split, last, center * -1., right));
+                stack.push((first, split, left, center));
+            }
+        }
+    }
+    out
+}
+
+impl Plotter {
+    pub fn quadratic_bezier<C>(&self, start: C, control: C, end: C) -> Path2D
+    where
+        C: Into<Vector2D>,
+    {
+        let (start, control, end) = (start.into(), control.into(), end.into());
+
+        Path2D::new(|builder| {
+            builder.move_to(self.screen_coord(start));
+            builder.quadratic_curve_to(self.screen_coord(control), self.screen_coord(end));
+        })
+    }
+
+    pub fn cubic_bezier<C>(&self, start: C, control_1: C, control_2: C, end: C) -> Path2D
+    where
+        C: Into<Vector2D>,
+    {
+        self.beziers(&[[start.into(), control_1.into(), control_2.into(), end.into()]])
+    }
+
+    /// Chains cubic Bézier segments, starting a new sub-path when they are not contiguous
+    pub fn beziers(&self, segments: &[CubicBezier]) -> Path2D {
+        Path2D::new(|builder| {
+            let mut current = None;
+            for &[start, c1, c2, end] in segments {
+                if current != Some(start) {
+                    builder.move_to(self.screen_coord(start));
+                }
+                builder.bezier_curve_to(
+                    self.screen_coord(c1),
+                    self.screen_coord(c2),
+                    self.screen_coord(end),
+                );
+                current = Some(end);
+            }
+        })
+    }
+
+    /// Centripetal Catmull-Rom spline through the points
+    pub fn catmull_rom<I, C>(&self, points: I) -> Path2D
+    where
+        C: Into<Vector2D>,
+        I: IntoIterator<Item = C>,
+    {
+        let points: Vec<Vector2D> = points.into_iter().map(Into::into).collect();
+        self.beziers(&catmull_rom(&points))
+    }
+
+    /// Natural cubic spline through the points
+    pub fn natural_cubic<I, C>(&self, points: I) -> Path2D
+    where
+        C: Into<Vector2D>,
+        I: IntoIterator<Item = C>,
+    {
+        let points: Vec<Vector2D> = points.into_iter().map(Into::into).collect();
+        self.beziers(&natural_cubic(&points))
+    }
+
+    ///
+    /// Same as Plotter::path, but the polyline is replaced by fewer Bézier segments.
+    /// The tolerance is in pixels.
+    ///
+    pub fn fitted_path<I, C>(&self, points: I, tolerance: f64) -> Path2D
+    where
+        C: Into<Vector2D>,
+        I: IntoIterator<Item = C>,
+    {
+        let points: Vec<Vector2D> = points.into_iter().map(Into::into).collect();
+        self.beziers(&fit_bezier(&points, tolerance / self.scale))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn knots() -> Vec<Vector2D> {
+        [
+            (0., 0.),
+            (1., 2.),
+            (1.5, 2.),
+            (3., -1.),
+            (4., 0.5),
+            (4., 3.),
+        ]
+        .into_iter()
+        .map(Into::into)
+        .collect()
+    }
+
+    /// Consecutive segments share their ends, which are the knots
+    fn assert_through_knots(segments: &[CubicBezier], knots: &[Vector2D]) {
+        assert_eq!(segments.len(), knots.len() - 1);
+        for (segment, w) in segments.iter().zip(knots.windows(2)) {
+            assert!((bezier_point(segment, 0.) - w[0]).norm() < 1e-12);
+            assert!((bezier_point(segment, 1.) - w[1]).norm() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn catmull_rom_through_knots() {
+        let knots = knots();
+        let segments = catmull_rom(&knots);
+        assert_through_knots(&segments, &knots);
+
+        // The tangent is continuous at the inner knots
+        for w in segments.windows(2) {
+            let (before, after) = (bezier_derivative(&w[0], 1.), bezier_derivative(&w[1], 0.));
+            assert!(before.normalize().dot(after.normalize()) > 1. - 1e-9);
+        }
+    }
+
+    #[test]
+    fn natural_cubic_through_knots() {
+        let knots = knots();
+        let segments = natural_cubic(&knots);
+        assert_through_knots(&segments, &knots);
+
+        // No curvature at the ends
+        let (first, last) = (segments[0], segments[segments.len() - 1]);
+        assert!(bezier_second_derivative(&first, 0.).norm() < 1e-9);
+        assert!(bezier_second_derivative(&last, 1.).norm() < 1e-9);
+    }
+
+    #[test]
+    fn fit_noisy_dense_input() {
+        // A zigzag at the scale of the tolerance splits a lot
+        let points: Vec<_> = (0..20_000)
+            .map(|i| {
+                let x = i as f64 * 1e-3;
+                let noise = if i % 2 == 0 { 1e-3 } else { -1e-3 };
+                vec2d(x, x.sin() + noise)
+            })
+            .collect();
+
+        let segments = fit_bezier(&points, 1e-4);
+        assert!(!segments.is_empty());
+        assert!((segments[0][0] - points[0]).norm() < 1e-12);
+        assert!((segments[segments.len() - 1][3] - points[points.len() - 1]).norm() < 1e-12);
+        for w in segments.windows(2) {
+            assert_eq!(w[0][3], w[1][0]);
+        }
+    }
+}
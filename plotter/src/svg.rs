@@ -0,0 +1,189 @@
+//! Drawing canvas geometries to SVG, for figures.
+//!
+//! The frames are tessellated by iced, so the shapes are written as the triangles covering
+//! them. The consecutive triangles of a color are merged in a single path, so that no seams
+//! show between them once the SVG is rendered. Text stays text, in a sans-serif font.
+
+use crate::raster::{geometries, linear_to_srgb};
+use iced_graphics::{
+    alignment::{Horizontal, Vertical},
+    triangle::{ColoredVertex2D, Mesh2D},
+    widget::canvas::{Geometry, Program},
+    Color, Primitive, Size,
+};
+use std::fmt::Write;
+
+/// Draws `program` as the canvas would in a window of `size`, with the default light theme
+pub fn draw_program<P, Message>(program: &P, size: Size<u32>) -> String
+where
+    P: Program<Message>,
+{
+    to_svg(geometries(program, size), size, Color::WHITE)
+}
+
+/// Writes the geometries on top of each other, in order, on an opaque background
+pub fn to_svg(geometries: Vec<Geometry>, size: Size<u32>, background: Color) -> String {
+    let mut document = Document::default();
+    let (width, height) = (size.width, size.height);
+
+    writeln!(
+        document.svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    )
+    .unwrap();
+    writeln!(
+        document.svg,
+        r#"<rect width="100%" height="100%" fill="{}"/>"#,
+        rgb([background.r, background.g, background.b])
+    )
+    .unwrap();
+
+    for geometry in geometries {
+        document.primitive(geometry.into_primitive());
+    }
+
+    document.svg.push_str("</svg>\n");
+    document.svg
+}
+
+/// Color of an sRGB triplet
+fn rgb([r, g, b]: [f32; 3]) -> String {
+    let channel = |c: f32| (c * 255.).round().clamp(0., 255.) as u8;
+    format!("#{:02x}{:02x}{:02x}", channel(r), channel(g), channel(b))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[derive(Default)]
+struct Document {
+    svg: String,
+    /// Number of clip paths, to give them unique ids
+    clips: usize,
+}
+
+impl Document {
+    fn primitive(&mut self, primitive: Primitive) {
+        match primitive {
+            Primitive::Group { primitives } => {
+                // Like the renderers of iced, the text goes on top of the meshes
+                let (text, meshes): (Vec<_>, Vec<_>) = primitives
+                    .into_iter()
+                    .partition(|p| matches!(p, Primitive::Text { .. }));
+
+                for p in meshes.into_iter().chain(text) {
+                    self.primitive(p);
+                }
+            }
+            Primitive::Translate {
+                translation,
+                content,
+            } => {
+                writeln!(
+                    self.svg,
+                    r#"<g transform="translate({} {})">"#,
+                    translation.x, translation.y
+                )
+                .unwrap();
+                self.primitive(*content);
+                self.svg.push_str("</g>\n");
+            }
+            Primitive::Clip { bounds, content } => {
+                let id = self.clips;
+                self.clips += 1;
+
+                writeln!(
+                    self.svg,
+                    r#"<clipPath id="clip{id}"><rect x="{}" y="{}" width="{}" height="{}"/></clipPath>"#,
+                    bounds.x, bounds.y, bounds.width, bounds.height
+                )
+                .unwrap();
+                writeln!(self.svg, r#"<g clip-path="url(#clip{id})">"#).unwrap();
+                self.primitive(*content);
+                self.svg.push_str("</g>\n");
+            }
+            Primitive::Cached { cache } => self.primitive((*cache).clone()),
+            Primitive::SolidMesh { buffers, .. } => self.mesh(&buffers),
+            Primitive::Text {
+                content,
+                bounds,
+                color,
+                size,
+                horizontal_alignment,
+                vertical_alignment,
+                ..
+            } => {
+                let anchor = match horizontal_alignment {
+                    Horizontal::Left => "start",
+                    Horizontal::Center => "middle",
+                    Horizontal::Right => "end",
+                };
+                let baseline = match vertical_alignment {
+                    Vertical::Top => "text-before-edge",
+                    Vertical::Center => "central",
+                    Vertical::Bottom => "text-after-edge",
+                };
+
+                writeln!(
+                    self.svg,
+                    r#"<text x="{:.2}" y="{:.2}" font-family="sans-serif" font-size="{size}" fill="{}" fill-opacity="{}" text-anchor="{anchor}" dominant-baseline="{baseline}">{}</text>"#,
+                    bounds.x,
+                    bounds.y,
+                    rgb([color.r, color.g, color.b]),
+                    color.a,
+                    escape(&content)
+                )
+                .unwrap();
+            }
+            // Not produced by the plots
+            _ => {}
+        }
+    }
+
+    fn mesh(&mut self, mesh: &Mesh2D<ColoredVertex2D>) {
+        let mut path = String::new();
+        let mut color = None;
+
+        for triangle in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize].position);
+
+            let area = (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]);
+            if area == 0. {
+                continue;
+            }
+
+            let triangle_color = mesh.vertices[triangle[0] as usize].color;
+            if color != Some(triangle_color) {
+                if let Some(color) = color {
+                    self.fill(&path, color);
+                }
+                path.clear();
+                color = Some(triangle_color);
+            }
+
+            write!(
+                path,
+                "M{:.2} {:.2}L{:.2} {:.2}L{:.2} {:.2}Z",
+                a[0], a[1], b[0], b[1], c[0], c[1]
+            )
+            .unwrap();
+        }
+
+        if let Some(color) = color {
+            self.fill(&path, color);
+        }
+    }
+
+    /// Fills a path with a color of the meshes, which are in linear RGB
+    fn fill(&mut self, path: &str, [r, g, b, alpha]: [f32; 4]) {
+        let color = rgb([r, g, b].map(linear_to_srgb));
+        writeln!(
+            self.svg,
+            r#"<path d="{path}" fill="{color}" fill-opacity="{alpha}"/>"#
+        )
+        .unwrap();
+    }
+}
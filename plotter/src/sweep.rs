@@ -0,0 +1,334 @@
+//! Automatic parameter sweeps.
+//!
+//! A [`Sweeper`] animates one parameter of the application at a time: either between the bounds
+//! of its range, or through a list of values with a pause on each. The application turns the
+//! swept values into its own messages, so any numeric parameter can be swept.
+
+use crate::{
+    animation::{cycle, lerp, ping_pong, ClockMessage},
+    Clock,
+};
+use iced_native::{
+    overlay::menu,
+    subscription::Subscription,
+    widget::{button, container, pick_list, scrollable, slider, text, text_input},
+    Element,
+};
+use std::{fmt, ops::RangeInclusive};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SweepMode {
+    /// Starts over from the beginning at each pass
+    Loop,
+    /// Goes back and forth
+    PingPong,
+    /// Stops at the end of the first pass
+    Once,
+}
+
+impl SweepMode {
+    pub const ALL: [SweepMode; 3] = [SweepMode::Loop, SweepMode::PingPong, SweepMode::Once];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SweepMode::Loop => "loop",
+            SweepMode::PingPong => "ping-pong",
+            SweepMode::Once => "once",
+        }
+    }
+}
+
+impl fmt::Display for SweepMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// What is swept, independently of the parameter
+#[derive(Clone, Debug, PartialEq)]
+pub enum Sweep {
+    /// Between the bounds of `range`, taking `duration` seconds for one pass
+    Range {
+        range: RangeInclusive<f64>,
+        duration: f64,
+    },
+    /// Through `values`, staying `pause` seconds on each
+    Values { values: Vec<f64>, pause: f64 },
+}
+
+impl Sweep {
+    /// Value after `time` seconds, and whether the sweep is over
+    pub fn value(&self, mode: SweepMode, time: f64) -> (f64, bool) {
+        match self {
+            Sweep::Range { range, duration } => {
+                let (t, finished) = match mode {
+                    SweepMode::Loop => (cycle(time, *duration), false),
+                    // One pass is going one way, the period is a round trip
+                    SweepMode::PingPong => (ping_pong(time, 2. * duration), false),
+                    SweepMode::Once => {
+                        let t = time / duration;
+                        (t.min(1.), t >= 1.)
+                    }
+                };
+                (lerp(*range.start(), *range.end(), t), finished)
+            }
+            Sweep::Values { values, pause } => {
+                let n = values.len();
+                if n == 0 {
+                    return (0., true);
+                }
+
+                let step = (time / pause).floor().max(0.) as usize;
+                let (index, finished) = match mode {
+                    SweepMode::Loop => (step % n, false),
+                    SweepMode::PingPong if n == 1 => (0, false),
+                    SweepMode::PingPong => {
+                        let i = step % (2 * n - 2);
+                        (if i < n { i } else { 2 * n - 2 - i }, false)
+                    }
+                    SweepMode::Once => (step.min(n - 1), step >= n),
+                };
+                (values[index], finished)
+            }
+        }
+    }
+}
+
+/// Parses a list of values separated by commas or spaces, ignoring what is not a number
+fn parse_values(values: &str) -> Vec<f64> {
+    values
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter_map(|v| v.parse().ok())
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub enum SweepMessage<P> {
+    Parameter(P),
+    Mode(SweepMode),
+    Duration(f64),
+    Values(String),
+    Toggle,
+    Clock(ClockMessage),
+}
+
+pub struct Sweeper<P> {
+    parameters: Vec<P>,
+    parameter: P,
+    mode: SweepMode,
+    /// Duration of a pass over the range, or pause on each value when there is a list
+    duration: f64,
+    /// The list of values of each parameter, in the order of `parameters`
+    values: Vec<String>,
+    clock: Clock,
+}
+
+impl<P> Sweeper<P>
+where
+    P: Clone + Eq + fmt::Display + 'static,
+{
+    /// `parameters` are the ones that can be swept, the first one is selected
+    pub fn new(parameters: Vec<P>) -> Self {
+        let parameter = parameters
+            .first()
+            .cloned()
+            .expect("at least one parameter is needed");
+
+        Self {
+            values: vec![String::new(); parameters.len()],
+            parameters,
+            parameter,
+            mode: SweepMode::PingPong,
+            duration: 5.,
+            clock: Clock::paused(),
+        }
+    }
+
+    /// Selects `parameter`, sweeping through `values` instead of its range
+    pub fn with_values(mut self, parameter: P, values: &[f64]) -> Self {
+        self.parameter = parameter;
+        *self.values_mut() = values
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.duration = 1.5;
+        self
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.clock.is_playing()
+    }
+
+    fn selected(&self) -> usize {
+        self.parameters
+            .iter()
+            .position(|p| *p == self.parameter)
+            .expect("the selected parameter can be swept")
+    }
+
+    /// List of values of the selected parameter
+    fn values(&self) -> &str {
+        &self.values[self.selected()]
+    }
+
+    fn values_mut(&mut self) -> &mut String {
+        let selected = self.selected();
+        &mut self.values[selected]
+    }
+
+    /// The values of the list that are in `range`, the range itself when there are none
+    fn sweep(&self, range: RangeInclusive<f64>) -> Sweep {
+        let values: Vec<_> = parse_values(self.values())
+            .into_iter()
+            .filter(|v| range.contains(v))
+            .collect();
+        if values.is_empty() {
+            Sweep::Range {
+                range,
+                duration: self.duration,
+            }
+        } else {
+            Sweep::Values {
+                values,
+                pause: self.duration,
+            }
+        }
+    }
+
+    ///
+    /// Returns the parameter to change and its new value at each frame of a running sweep.
+    /// `range` gives the current range of a parameter.
+    ///
+    pub fn update<F>(&mut self, message: SweepMessage<P>, range: F) -> Option<(P, f64)>
+    where
+        F: Fn(&P) -> RangeInclusive<f64>,
+    {
+        match message {
+            SweepMessage::Parameter(p) => self.parameter = p,
+            SweepMessage::Mode(m) => self.mode = m,
+            SweepMessage::Duration(d) => self.duration = d,
+            SweepMessage::Values(v) => *self.values_mut() = v,
+            SweepMessage::Toggle => {
+                self.clock.update(ClockMessage::Reset);
+                self.clock.update(ClockMessage::Toggle);
+            }
+            SweepMessage::Clock(m) => {
+                self.clock.update(m);
+
+                // Rounding may put the value slightly past the range
+                let range = range(&self.parameter);
+                let (value, finished) = self
+                    .sweep(range.clone())
+                    .value(self.mode, self.clock.time());
+                let value = value.clamp(*range.start(), *range.end());
+                if finished {
+                    self.clock.update(ClockMessage::Toggle);
+                }
+
+                return Some((self.parameter.clone(), value));
+            }
+        }
+
+        None
+    }
+
+    pub fn subscription(&self) -> Subscription<SweepMessage<P>> {
+        self.clock.subscription().map(SweepMessage::Clock)
+    }
+
+    /// Parameter, mode, duration and values selection, with a start/stop button
+    pub fn controls<'a, Message, Renderer>(
+        &'a self,
+        on_message: fn(SweepMessage<P>) -> Message,
+    ) -> Element<'a, Message, Renderer>
+    where
+        Message: Clone + 'a,
+        Renderer: iced_native::text::Renderer + 'a,
+        Renderer::Theme: button::StyleSheet
+            + slider::StyleSheet
+            + text::StyleSheet
+            + text_input::StyleSheet
+            + pick_list::StyleSheet
+            + scrollable::StyleSheet
+            + menu::StyleSheet
+            + container::StyleSheet,
+        <Renderer::Theme as menu::StyleSheet>::Style:
+            From<<Renderer::Theme as pick_list::StyleSheet>::Style>,
+    {
+        let has_values = !parse_values(self.values()).is_empty();
+        let duration = if has_values { "Pause" } else { "Duration" };
+
+        iced_native::widget::row(vec![
+            text("Sweep").into(),
+            pick_list(
+                &self.parameters[..],
+                Some(self.parameter.clone()),
+                move |p| on_message(SweepMessage::Parameter(p)),
+            )
+            .into(),
+            pick_list(&SweepMode::ALL[..], Some(self.mode), move |m| {
+                on_message(SweepMessage::Mode(m))
+            })
+            .into(),
+            text(format!("{duration} ({:4.1}s)", self.duration)).into(),
+            slider(0.2..=20.0, self.duration, move |d| {
+                on_message(SweepMessage::Duration(d))
+            })
+            .step(0.1)
+            .into(),
+            text_input("Values, e.g. 2, 21, 44", self.values())
+                .on_input(move |v| on_message(SweepMessage::Values(v)))
+                .into(),
+            button(text(if self.is_running() { "Stop" } else { "Start" }))
+                .on_press(on_message(SweepMessage::Toggle))
+                .into(),
+        ])
+        .spacing(5)
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_per_parameter() {
+        let mut sweeper = Sweeper::new(vec!["a", "b"]).with_values("a", &[2., 3.]);
+        assert_eq!(sweeper.values(), "2, 3");
+
+        sweeper.update(SweepMessage::Parameter("b"), |_| 0.0..=1.0);
+        assert_eq!(sweeper.values(), "");
+        assert_eq!(
+            sweeper.sweep(0.0..=1.0),
+            Sweep::Range {
+                range: 0.0..=1.0,
+                duration: sweeper.duration
+            }
+        );
+
+        sweeper.update(SweepMessage::Values("0.5".into()), |_| 0.0..=1.0);
+        sweeper.update(SweepMessage::Parameter("a"), |_| 0.0..=1.0);
+        assert_eq!(sweeper.values(), "2, 3");
+    }
+
+    #[test]
+    fn values_out_of_range_skipped() {
+        let sweeper = Sweeper::new(vec!["a"]).with_values("a", &[2., 5., 9., 21., 44.]);
+        assert_eq!(
+            sweeper.sweep(2.0..=9.0),
+            Sweep::Values {
+                values: vec![2., 5., 9.],
+                pause: sweeper.duration
+            }
+        );
+        assert_eq!(
+            sweeper.sweep(10.0..=20.0),
+            Sweep::Range {
+                range: 10.0..=20.0,
+                duration: sweeper.duration
+            }
+        );
+    }
+}
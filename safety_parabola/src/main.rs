@@ -1,20 +1,221 @@
-use either::Either;
 use iced::{
-    executor,
-    widget::{canvas, canvas::Stroke, pick_list, slider, Column, Row, Text},
-    Application, Color, Command, Length, Theme,
+    alignment::Horizontal,
+    widget::canvas::{Frame, LineDash, Stroke, Text},
+    Color, Size,
+};
+use plotter::{
+    animation,
+    app::Key,
+    envelope::envelope,
+    handle::Constraint,
+    linspace, ode, parameters,
+    params::{Choice, Spec, Value},
+    projection::Projection,
+    vec2d, Camera, Dual, Handle, Handles, Plotter, Vector2D, Visualization,
+};
+use std::{
+    cell::RefCell,
+    f64::consts::{FRAC_PI_2, FRAC_PI_3, FRAC_PI_4, PI, TAU},
+    ops::RangeInclusive,
 };
-use plotter::{linspace, Plotter};
 
 const RESOLUTION: usize = 100;
-const DEFAULT_SCALE: f64 = 75.;
 const EARTH_G: f64 = 9.81;
+const FEET_PER_METER: f64 = 1. / 0.3048;
 const DEFAULT_SPACING: ParabolaSpacing = ParabolaSpacing::EqualAngle;
+const DEFAULT_V0: f64 = 10.;
+const V0_RANGE: RangeInclusive<f64> = 0.1..=20.0;
+/// Room around the safety curve, relative to its size
+const VIEW_MARGIN: f64 = 1.15;
+/// Time step of the integration of the trajectories with air resistance, in seconds
+const TIME_STEP: f64 = 0.005;
+/// Bound on the number of steps of a trajectory, for the very slow falls
+const MAX_STEPS: usize = 20_000;
+/// Number of shots, between horizontal and vertical, whose upper bound is the safety curve
+const ENVELOPE_SHOTS: usize = 300;
+/// Distance in meters under which a target is on the safety curve
+const ON_CURVE: f64 = 0.05;
+/// Height in pixels of a line of the labels in the corner
+const LINE_HEIGHT: f64 = 22.;
+/// Approximate number of graduations along the width of the view
+const TICKS: f64 = 12.;
+/// Half length in pixels of the graduations of the axes
+const TICK_SIZE: f64 = 4.;
+/// Number of vertical and horizontal lines of the wireframe of the safety paraboloid
+const MERIDIANS: usize = 16;
+const RINGS: usize = 8;
+/// Number of angles on a line of the readout
+const ANGLES_PER_LINE: usize = 10;
+
+/// Highest point reachable when shooting at `v0` without air resistance, it is the apex of
+/// the safety parabola
+fn apex(params: &Params, v0: f64) -> f64 {
+    v0 * v0 / (2. * gravity(params))
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum Gravity {
+    Earth,
+    Moon,
+    Mars,
+    Jupiter,
+    /// From the custom gravity parameter
+    Custom,
+}
+
+impl Gravity {
+    /// Acceleration of gravity at the surface, in m/s²
+    fn acceleration(self) -> Option<f64> {
+        match self {
+            Gravity::Earth => Some(EARTH_G),
+            Gravity::Moon => Some(1.62),
+            Gravity::Mars => Some(3.71),
+            Gravity::Jupiter => Some(24.79),
+            Gravity::Custom => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Gravity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Gravity::Earth => "Earth",
+            Gravity::Moon => "Moon",
+            Gravity::Mars => "Mars",
+            Gravity::Jupiter => "Jupiter",
+            Gravity::Custom => "custom",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl Choice for Gravity {
+    const ALL: &'static [Self] = &[
+        Gravity::Earth,
+        Gravity::Moon,
+        Gravity::Mars,
+        Gravity::Jupiter,
+        Gravity::Custom,
+    ];
+}
+
+/// Units in which the lengths and speeds are shown, they are always stored in meters
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum Units {
+    Si,
+    Imperial,
+}
+
+impl Units {
+    /// Number of shown units of length in a meter
+    fn length(self) -> f64 {
+        match self {
+            Units::Si => 1.,
+            Units::Imperial => FEET_PER_METER,
+        }
+    }
+
+    fn length_unit(self) -> &'static str {
+        match self {
+            Units::Si => " m",
+            Units::Imperial => " ft",
+        }
+    }
+
+    fn speed_unit(self) -> &'static str {
+        match self {
+            Units::Si => " m/s",
+            Units::Imperial => " ft/s",
+        }
+    }
+
+    fn acceleration_unit(self) -> &'static str {
+        match self {
+            Units::Si => " m/s²",
+            Units::Imperial => " ft/s²",
+        }
+    }
+
+    /// A length in meters, in these units
+    fn show(self, meters: f64) -> String {
+        format!("{:.2}{}", meters * self.length(), self.length_unit())
+    }
+}
+
+impl std::fmt::Display for Units {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Units::Si => "SI",
+            Units::Imperial => "imperial",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl Choice for Units {
+    const ALL: &'static [Self] = &[Units::Si, Units::Imperial];
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum AirResistance {
+    None,
+    /// Proportional to the speed, for slow projectiles
+    Linear,
+    /// Proportional to the square of the speed, for fast projectiles
+    Quadratic,
+}
+
+impl AirResistance {
+    /// Unit of the drag coefficient, for the drag to be an acceleration
+    fn unit(self, units: Units) -> &'static str {
+        match (self, units) {
+            (AirResistance::None, _) => "",
+            (AirResistance::Linear, _) => " /s",
+            (AirResistance::Quadratic, Units::Si) => " /m",
+            (AirResistance::Quadratic, Units::Imperial) => " /ft",
+        }
+    }
+
+    /// Number of shown units of the drag coefficient in the stored one, per meter or per second
+    fn scale(self, units: Units) -> f64 {
+        match self {
+            AirResistance::Quadratic => 1. / units.length(),
+            _ => 1.,
+        }
+    }
+}
+
+impl std::fmt::Display for AirResistance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            AirResistance::None => "none",
+            AirResistance::Linear => "linear",
+            AirResistance::Quadratic => "quadratic",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl Choice for AirResistance {
+    const ALL: &'static [Self] = &[
+        AirResistance::None,
+        AirResistance::Linear,
+        AirResistance::Quadratic,
+    ];
+}
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
 enum ParabolaSpacing {
     EqualXIntersect,
     EqualAngle,
+    /// The shots stay in the air for evenly spaced durations
+    EqualTimeOfFlight,
+    /// The apexes are at evenly spaced heights
+    EqualHeight,
+    /// The shots touch the safety parabola at evenly spaced x
+    EqualTangency,
+    /// Uniformly random angles, from the seed
+    Random,
 }
 
 impl std::fmt::Display for ParabolaSpacing {
@@ -22,194 +223,1390 @@ impl std::fmt::Display for ParabolaSpacing {
         let s = match self {
             ParabolaSpacing::EqualXIntersect => "equal x intersect",
             ParabolaSpacing::EqualAngle => "equal angles",
+            ParabolaSpacing::EqualTimeOfFlight => "equal time of flight",
+            ParabolaSpacing::EqualHeight => "equal maximum height",
+            ParabolaSpacing::EqualTangency => "equal tangency point",
+            ParabolaSpacing::Random => "random",
         };
         write!(f, "{}", s)
     }
 }
 
-struct SafetyParabola {
-    state: State,
+impl Choice for ParabolaSpacing {
+    const ALL: &'static [Self] = &[
+        ParabolaSpacing::EqualXIntersect,
+        ParabolaSpacing::EqualAngle,
+        ParabolaSpacing::EqualTimeOfFlight,
+        ParabolaSpacing::EqualHeight,
+        ParabolaSpacing::EqualTangency,
+        ParabolaSpacing::Random,
+    ];
 }
 
-struct State {
-    plot_cache: canvas::Cache,
-    v0: f64,
-    count: usize,
-    spacing: ParabolaSpacing,
+/// Uniform numbers in `[0, 1)` generated from `seed` with SplitMix64
+fn random(seed: u64) -> impl Iterator<Item = f64> {
+    let mut state = seed;
+    std::iter::repeat_with(move || {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    })
 }
 
-#[derive(Debug, Clone, Copy)]
-enum Message {
-    SetV0(f64),
-    SetCount(usize),
-    SetSpacing(ParabolaSpacing),
+parameters! {
+    struct Params: Param |params| {
+        units: Units = Units::Si => Spec::choice::<Units>("Units"),
+        gravity: Gravity = Gravity::Earth => Spec::choice::<Gravity>("Gravity"),
+        custom_gravity: f64 = EARTH_G => Spec::float("Custom gravity", 0.1..=30.0)
+            .scale(params.units.length())
+            .step(0.01)
+            .unit(params.units.acceleration_unit()),
+        count: usize = 10 => Spec::int("Count", 4..=100).step(2.),
+        v0: f64 = DEFAULT_V0 => Spec::float("v0", V0_RANGE)
+            .scale(params.units.length())
+            .step(0.1)
+            .unit(params.units.speed_unit()),
+        spacing: ParabolaSpacing = DEFAULT_SPACING => Spec::choice::<ParabolaSpacing>("Spacing"),
+        seed: usize = 1 => Spec::int("Seed", 0..=1000),
+        angle_readout: bool = false => Spec::toggle("Angle readout"),
+        computed_envelope: bool = false => Spec::toggle("Computed envelope"),
+        air_resistance: AirResistance = AirResistance::None
+            => Spec::choice::<AirResistance>("Air resistance"),
+        drag_coefficient: f64 = 0.1 => Spec::float("Drag coefficient", 0.01..=1.0)
+            .scale(params.air_resistance.scale(params.units))
+            .step(0.01)
+            .unit(params.air_resistance.unit(params.units)),
+        launch_height: f64 = 0. => Spec::float("Launch height", view_range(params, 0., 5.))
+            .scale(params.units.length())
+            .step(0.1)
+            .unit(params.units.length_unit()),
+        slope: f64 = 0. => Spec::float("Slope of the terrain", -FRAC_PI_3..=FRAC_PI_3)
+            .scale(180. / PI)
+            .step(1.)
+            .precision(0)
+            .unit("°"),
+        terrain: bool = false => Spec::toggle("Terrain"),
+        target_x: f64 = 3. => Spec::float("Target x", view_range(params, -10., 10.))
+            .scale(params.units.length())
+            .step(0.1)
+            .unit(params.units.length_unit()),
+        target_y: f64 = 1. => Spec::float("Target y", view_range(params, -5., 10.))
+            .scale(params.units.length())
+            .step(0.1)
+            .unit(params.units.length_unit()),
+        target: bool = false => Spec::toggle("Target"),
+        projectiles: bool = false => Spec::toggle("Projectiles in flight"),
+        apexes: bool = false => Spec::toggle("Apexes"),
+        foci: bool = false => Spec::toggle("Foci and directrix"),
+        view_3d: bool = false => Spec::toggle("3D"),
+        azimuths: usize = 8 => Spec::int("Azimuths", 1..=24),
+        yaw: f64 = 0.6 => Spec::float("Yaw", 0.0..=TAU)
+            .scale(180. / PI)
+            .step(1.)
+            .precision(0)
+            .unit("°"),
+        pitch: f64 = 0.4 => Spec::float("Pitch", -FRAC_PI_2..=FRAC_PI_2)
+            .scale(180. / PI)
+            .step(1.)
+            .precision(0)
+            .unit("°"),
+        projection: Projection = Projection::Orthographic
+            => Spec::choice::<Projection>("Projection"),
+    }
 }
 
-pub fn main() -> iced::Result {
-    #[cfg(target_arch = "wasm32")]
-    let platform_specific = {
-        std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+/// Acceleration of gravity in m/s², from the preset or the custom one
+fn gravity(params: &Params) -> f64 {
+    params
+        .gravity
+        .acceleration()
+        .unwrap_or(params.custom_gravity)
+}
 
-        iced::window::PlatformSpecific {
-            target: Some("iced_root".into()),
-        }
+/// Range of positions in meters, given as on Earth and growing with the view as the shots, in
+/// 1/g
+fn view_range(params: &Params, low: f64, high: f64) -> RangeInclusive<f64> {
+    let factor = EARTH_G / gravity(params);
+    low * factor..=high * factor
+}
+
+/// Acceleration of the projectile moving at `speed`, the drag opposes the movement
+fn acceleration(params: &Params, speed: Vector2D) -> Vector2D {
+    let (g, k) = (gravity(params), params.drag_coefficient);
+    let drag = match params.air_resistance {
+        AirResistance::None => 0.,
+        AirResistance::Linear => k,
+        AirResistance::Quadratic => k * speed.norm(),
     };
 
-    #[cfg(not(target_arch = "wasm32"))]
-    let platform_specific = iced::window::PlatformSpecific::default();
+    vec2d(0., -g) - speed * drag
+}
+
+///
+/// Positions and speeds along the shot at `theta`, as `[x, y, vx, vy]` every `TIME_STEP`. It is
+/// integrated until it leaves the view by the bottom or the sides, `bounds` being the half
+/// extents of the view.
+///
+fn shot(params: &Params, theta: f64, bounds: Vector2D) -> Vec<[f64; 4]> {
+    let speed = vec2d(theta.cos(), theta.sin()) * params.v0;
+    let motion = |_, [_, _, vx, vy]: [f64; 4]| {
+        let a = acceleration(params, vec2d(vx, vy));
+        [vx, vy, a.x(), a.y()]
+    };
+
+    let start = [0., params.launch_height, speed.x(), speed.y()];
+    ode::integrate(motion, 0., start, TIME_STEP)
+        .take(MAX_STEPS)
+        .map(|(_, state)| state)
+        .take_while(|&[x, y, _, _]| y >= -bounds.y() && x.abs() <= bounds.x())
+        .collect()
+}
+
+/// Highest point of the shot at `theta`, where it stops going up
+fn shot_apex(params: &Params, theta: f64, bounds: Vector2D) -> Option<Vector2D> {
+    shot(params, theta, bounds)
+        .into_iter()
+        .find(|&[_, _, _, vy]| vy <= 0.)
+        .map(|[x, y, _, _]| vec2d(x, y))
+}
+
+fn trajectory(params: &Params, theta: f64, bounds: Vector2D) -> Vec<Vector2D> {
+    shot(params, theta, bounds)
+        .into_iter()
+        .map(|[x, y, _, _]| vec2d(x, y))
+        .collect()
+}
+
+/// Highest point reachable when shooting at `v0`, by shooting straight up
+fn apex_with_drag(params: &Params, v0: f64) -> f64 {
+    let (g, k) = (gravity(params), params.drag_coefficient);
+    match params.air_resistance {
+        AirResistance::None => apex(params, v0),
+        AirResistance::Linear => v0 / k - g / (k * k) * (k * v0 / g).ln_1p(),
+        AirResistance::Quadratic => (k * v0 * v0 / g).ln_1p() / (2. * k),
+    }
+}
 
-    SafetyParabola::run(iced::Settings {
-        antialiasing: true,
-        window: iced::window::Settings {
-            platform_specific,
-            ..Default::default()
-        },
-        ..Default::default()
+/// The speed whose apex is `height`, the apex grows with the speed
+fn speed_for_apex(params: &Params, height: f64) -> f64 {
+    let (mut low, mut high) = (*V0_RANGE.start(), *V0_RANGE.end());
+    for _ in 0..60 {
+        let middle = (low + high) / 2.;
+        if apex_with_drag(params, middle) < height {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    (low + high) / 2.
+}
+
+/// Where a target is relative to the safety curve
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Reach {
+    Inside,
+    On,
+    Outside,
+}
+
+impl Reach {
+    /// From how far above the safety curve the target is
+    fn of(above: f64) -> Self {
+        if above > ON_CURVE {
+            Reach::Outside
+        } else if above < -ON_CURVE {
+            Reach::Inside
+        } else {
+            Reach::On
+        }
+    }
+}
+
+/// Height at `x` > 0 of the shot at `theta`, if it gets there before leaving the view
+fn height_at(params: &Params, theta: f64, x: f64, bounds: Vector2D) -> Option<f64> {
+    shot(params, theta, bounds).windows(2).find_map(|w| {
+        let ([x0, y0, ..], [x1, y1, ..]) = (w[0], w[1]);
+        (x0 <= x && x < x1).then(|| y0 + (y1 - y0) * (x - x0) / (x1 - x0))
     })
 }
 
-impl Application for SafetyParabola {
-    type Executor = executor::Default;
-    type Message = Message;
-    type Theme = Theme;
-    type Flags = ();
+///
+/// Where the target is relative to the safety curve, and the angles of the shots going through
+/// it. Without air resistance the angles are the roots of a quadratic in tan(θ), with it they
+/// are searched among the shots.
+///
+fn aim(params: &Params, bounds: Vector2D) -> (Reach, Vec<f64>) {
+    let Params {
+        v0,
+        launch_height,
+        target_x,
+        target_y,
+        ..
+    } = *params;
+    let g = gravity(params);
+    // The shots towards the left are symmetric to the ones towards the right
+    let (x, y) = (target_x.abs(), target_y - launch_height);
 
-    fn new(_: Self::Flags) -> (Self, iced::Command<Self::Message>) {
-        (
-            Self {
-                state: State::new(),
-            },
-            Command::none(),
+    let (reach, angles) = if x < 1e-9 {
+        // Only the vertical shot goes above the cannon
+        let reach = Reach::of(y - apex_with_drag(params, v0));
+        let angles = match reach {
+            Reach::Outside => Vec::new(),
+            _ => vec![FRAC_PI_2],
+        };
+        (reach, angles)
+    } else if params.air_resistance == AirResistance::None {
+        // y = x tan(θ) - k (1 + tan²(θ)), the safety parabola being y = v0² / 2g - k
+        let k = g * x * x / (2. * v0 * v0);
+        let above = y - (apex(params, v0) - k);
+        let reach = Reach::of(above);
+        let angles = match reach {
+            Reach::Outside => Vec::new(),
+            Reach::On => vec![(x / (2. * k)).atan()],
+            Reach::Inside => {
+                let root = (-4. * k * above).sqrt();
+                vec![
+                    ((x - root) / (2. * k)).atan(),
+                    ((x + root) / (2. * k)).atan(),
+                ]
+            }
+        };
+        (reach, angles)
+    } else {
+        let thetas: Vec<_> =
+            linspace(-FRAC_PI_2 + 0.01, FRAC_PI_2 - 0.01, ENVELOPE_SHOTS).collect();
+        let heights: Vec<_> = thetas
+            .iter()
+            .map(|&theta| height_at(params, theta, x, bounds))
+            .collect();
+
+        // The safety curve is the highest the shots go at x
+        let Some((top, highest)) = thetas
+            .iter()
+            .zip(&heights)
+            .filter_map(|(&theta, height)| Some((theta, (*height)?)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+        else {
+            return (Reach::Outside, Vec::new());
+        };
+
+        let reach = Reach::of(target_y - highest);
+        let angles = match reach {
+            Reach::Outside => Vec::new(),
+            Reach::On => vec![top],
+            Reach::Inside => {
+                let above = |height: Option<f64>| height.is_some_and(|h| h > target_y);
+                let crossings = thetas.windows(2).zip(heights.windows(2));
+                crossings
+                    .filter(|(_, h)| above(h[0]) != above(h[1]))
+                    .map(|(theta, h)| {
+                        let (mut low, mut high) = (theta[0], theta[1]);
+                        for _ in 0..30 {
+                            let middle = (low + high) / 2.;
+                            if above(height_at(params, middle, x, bounds)) == above(h[0]) {
+                                low = middle;
+                            } else {
+                                high = middle;
+                            }
+                        }
+                        (low + high) / 2.
+                    })
+                    .collect()
+            }
+        };
+        (reach, angles)
+    };
+
+    let angles = angles
+        .into_iter()
+        .map(|theta| if target_x < 0. { PI - theta } else { theta })
+        .collect();
+    (reach, angles)
+}
+
+/// A line of the labels in the top left corner
+fn label(plotter: &Plotter, bounds: Vector2D, line: usize, content: String, color: Color) -> Text {
+    Text {
+        horizontal_alignment: Horizontal::Left,
+        color,
+        ..plotter.text(
+            -bounds.x() * 0.95,
+            bounds.y() * 0.9 - line as f64 * LINE_HEIGHT / plotter.scale(),
+            content,
         )
     }
+}
 
-    fn title(&self) -> String {
-        "Safety Parabola".into()
-    }
-
-    fn update(&mut self, msg: Self::Message) -> iced::Command<Self::Message> {
-        match msg {
-            Message::SetV0(v0) => self.state.v0 = v0,
-            Message::SetCount(c) => self.state.count = c,
-            Message::SetSpacing(s) => self.state.spacing = s,
-        }
-        self.state.plot_cache.clear();
-
-        Command::none()
-    }
-
-    fn view(&self) -> iced::Element<'_, Self::Message> {
-        Column::with_children(vec![
-            Row::with_children(vec![
-                Text::new(format!("count ({:3})", self.state.count)).into(),
-                slider(4.0..=100.0, self.state.count as f64, |f: f64| {
-                    Message::SetCount(f.ceil() as usize)
-                })
-                .step(2.0)
-                .into(),
-            ])
-            .into(),
-            Row::with_children(vec![
-                Text::new(format!("v0 ({:7.2})", self.state.v0)).into(),
-                slider(0.1..=20.0, self.state.v0, Message::SetV0)
-                    .step(0.1)
-                    .into(),
-            ])
-            .into(),
-            Row::with_children(vec![
-                Text::new("Spacing").into(),
-                pick_list(
-                    &[
-                        ParabolaSpacing::EqualXIntersect,
-                        ParabolaSpacing::EqualAngle,
-                    ][..],
-                    Some(self.state.spacing),
-                    Message::SetSpacing,
-                )
-                .into(),
-            ])
-            .into(),
-            canvas(&self.state)
-                .width(Length::Fill)
-                .height(Length::Fill)
-                .into(),
-        ])
-        .into()
-    }
-}
-
-impl State {
-    fn new() -> Self {
-        Self {
-            plot_cache: canvas::Cache::new(),
-            v0: 10.,
-            count: 10,
-            spacing: DEFAULT_SPACING,
-        }
-    }
-}
-
-impl<Message> canvas::Program<Message> for State {
-    type State = ();
-
-    fn draw(
-        &self,
-        _state: &Self::State,
-        _theme: &Theme,
-        bounds: iced::Rectangle,
-        _cursor: canvas::Cursor,
-    ) -> Vec<canvas::Geometry> {
-        let v0 = self.v0;
-
-        vec![self.plot_cache.draw(bounds.size(), |frame| {
-            let iced::Size { width, height } = frame.size();
-            let plotter = Plotter::new(RESOLUTION, width as _, height as _, DEFAULT_SCALE);
-
-            let axis = plotter.axis();
-            frame.stroke(
-                &axis,
-                Stroke::default().with_width(2.0).with_color(Color::BLACK),
-            );
+/// Nice step between graduations, 1, 2 or 5 times a power of ten, close to `raw`
+fn graduation_step(raw: f64) -> f64 {
+    let power = 10f64.powf(raw.log10().floor());
+    let mantissa = match raw / power {
+        m if m < 1.5 => 1.,
+        m if m < 3.5 => 2.,
+        m if m < 7.5 => 5.,
+        _ => 10.,
+    };
+    mantissa * power
+}
 
-            let make_parabola = |th: f64| {
-                let a = -EARTH_G / (2. * v0 * v0 * th.cos() * th.cos());
-                let b = th.tan();
+/// Graduations of the axes labelled in the shown unit of length, `bounds` being the half extents
+/// of the view
+fn draw_graduations(units: Units, frame: &mut Frame, plotter: &Plotter, bounds: Vector2D) {
+    let step = graduation_step(plotter.width() * units.length() / TICKS);
+    let decimals = (-step.log10().floor()).max(0.) as usize;
+    let (tick, spacing) = (TICK_SIZE / plotter.scale(), step / units.length());
+    let small = |text: Text| Text { size: 14., ..text };
 
-                plotter.parabola(a, b, 0.)
-            };
+    let ticks = |half: f64| {
+        let last = (half / spacing).floor() as i64;
+        (-last..=last).filter(|&i| i != 0)
+    };
+    let stroke = Stroke::default().with_width(1.5).with_color(Color::BLACK);
+    for i in ticks(bounds.x()) {
+        let x = i as f64 * spacing;
+        frame.stroke(
+            &plotter.path([vec2d(x, -tick), vec2d(x, tick)]),
+            stroke.clone(),
+        );
+        let content = format!("{:.*}", decimals, i as f64 * step);
+        frame.fill_text(small(plotter.text(x, -4. * tick, content)));
+    }
+    for i in ticks(bounds.y()) {
+        let y = i as f64 * spacing;
+        frame.stroke(
+            &plotter.path([vec2d(-tick, y), vec2d(tick, y)]),
+            stroke.clone(),
+        );
+        let content = format!("{:.*}", decimals, i as f64 * step);
+        frame.fill_text(Text {
+            horizontal_alignment: Horizontal::Left,
+            ..small(plotter.text(2. * tick, y, content))
+        });
+    }
 
-            let x_max = v0 * v0 / EARTH_G;
-            let thetas = match self.spacing {
-                ParabolaSpacing::EqualXIntersect => Either::Left(
-                    linspace(-x_max * 0.95, x_max * 0.95, self.count / 2)
-                        .filter(|&x| x != 0.)
-                        .flat_map(|x| {
-                            let th = (EARTH_G * x / (v0 * v0)).asin() / 2.;
-                            [th, th + std::f64::consts::FRAC_PI_2]
-                        }),
-                ),
-                ParabolaSpacing::EqualAngle => Either::Right(
-                    linspace(0.001, std::f64::consts::PI, self.count)
-                        .filter(|&x| x != std::f64::consts::FRAC_PI_2),
-                ),
+    let unit = units.length_unit().trim();
+    frame.fill_text(Text {
+        horizontal_alignment: Horizontal::Right,
+        ..small(plotter.text(bounds.x() - tick, 7. * tick, format!("x ({unit})")))
+    });
+    frame.fill_text(Text {
+        horizontal_alignment: Horizontal::Left,
+        ..small(plotter.text(2. * tick, -bounds.y() + 4. * tick, format!("y ({unit})")))
+    });
+}
+
+/// Where and when a shot touches the envelope
+#[derive(Clone, Copy)]
+struct Touch {
+    theta: f64,
+    /// Time in seconds since the launch
+    time: f64,
+    point: Vector2D,
+}
+
+///
+/// Points where the shots towards the right touch their envelope, from the farthest to the
+/// apex. The envelope is the boundary of the points that can be
+/// reached, without air resistance it is the safety parabola.
+///
+/// As in [`family_envelope`](plotter::envelope::family_envelope), a shot touches the envelope
+/// when its speed is colinear with the derivative of its position with respect to the angle.
+/// That derivative is approximated with the positions at the same time of the neighbouring
+/// shots, as all are integrated with the same steps.
+///
+fn envelope_points(params: &Params, bounds: Vector2D) -> Vec<Touch> {
+    let thetas: Vec<_> = linspace(0., FRAC_PI_2, ENVELOPE_SHOTS).collect();
+    let shots: Vec<_> = thetas
+        .iter()
+        .map(|&theta| shot(params, theta, bounds))
+        .collect();
+
+    let mut points: Vec<_> = shots
+        .windows(3)
+        .zip(&thetas[1..])
+        .filter_map(|(w, &theta)| {
+            let (before, shot, after) = (&w[0], &w[1], &w[2]);
+            let cross = |j: usize| {
+                let [_, _, vx, vy] = shot[j];
+                let dx = after[j][0] - before[j][0];
+                let dy = after[j][1] - before[j][1];
+                vx * dy - vy * dx
             };
 
-            for parabola in thetas.map(make_parabola) {
+            // All the shots start at the origin, the cross product is null there
+            let steps = before.len().min(shot.len()).min(after.len());
+            let j = (2..steps).find(|&j| cross(j - 1) * cross(j) <= 0.)?;
+
+            let (c0, c1) = (cross(j - 1), cross(j));
+            let t = if c0 == c1 { 0. } else { c0 / (c0 - c1) };
+            let [x0, y0, _, _] = shot[j - 1];
+            let [x1, y1, _, _] = shot[j];
+            Some(Touch {
+                theta,
+                time: (j as f64 - 1. + t) * TIME_STEP,
+                point: vec2d(x0 + (x1 - x0) * t, y0 + (y1 - y0) * t),
+            })
+        })
+        .collect();
+    // The vertical shot touches it at its apex, when it stops going up
+    let vertical = shot(params, FRAC_PI_2, bounds);
+    let rising = vertical
+        .iter()
+        .take_while(|&&[_, _, _, vy]| vy > 0.)
+        .count();
+    let apex = params.launch_height + apex_with_drag(params, params.v0);
+    points.push(Touch {
+        theta: FRAC_PI_2,
+        time: rising as f64 * TIME_STEP,
+        point: vec2d(0., apex),
+    });
+
+    points
+}
+
+/// The envelope through the given touches to the right, the shots to the left being symmetric
+/// to the ones to the right
+fn safety_curve(touches: &[Touch]) -> Vec<Vector2D> {
+    let right: Vec<_> = touches.iter().map(|touch| touch.point).collect();
+
+    right
+        .iter()
+        .map(|p| vec2d(-p.x(), p.y()))
+        .chain(right.iter().rev().skip(1).copied())
+        .collect()
+}
+
+///
+/// Farthest point of the terrain that can be reached towards the right, and the angle of the
+/// shot reaching it. It is where the envelope of the shots crosses the terrain, the shot
+/// landing there being the one touching the envelope at that point. With air resistance, the
+/// envelope is given by the `touches` of the shots.
+///
+fn best_landing(params: &Params, touches: &[Touch]) -> Option<(Vector2D, f64)> {
+    let Params {
+        v0, launch_height, ..
+    } = *params;
+    let (g, slope) = (gravity(params), params.slope.tan());
+
+    match params.air_resistance {
+        AirResistance::None => {
+            // Solving s(x) = x tan(α), with s the safety parabola
+            let (a, c) = (g / (2. * v0 * v0), launch_height + apex(params, v0));
+            let x = (-slope + (slope * slope + 4. * a * c).sqrt()) / (2. * a);
+
+            // The shot at θ touches the safety parabola at x = v0² / (g tan(θ))
+            Some((vec2d(x, x * slope), (v0 * v0 / (g * x)).atan()))
+        }
+        _ => touches.windows(2).find_map(|w| {
+            let (
+                Touch {
+                    theta: th0,
+                    point: p0,
+                    ..
+                },
+                Touch {
+                    theta: th1,
+                    point: p1,
+                    ..
+                },
+            ) = (w[0], w[1]);
+            let (d0, d1) = (p0.y() - p0.x() * slope, p1.y() - p1.x() * slope);
+            (d0 <= 0. && d1 > 0.).then(|| {
+                let t = d0 / (d0 - d1);
+                (p0 + (p1 - p0) * t, th0 + (th1 - th0) * t)
+            })
+        }),
+    }
+}
+
+/// Angles of the drawn shots, spaced for a launch from the ground without air resistance
+fn thetas(params: &Params) -> Vec<f64> {
+    let Params {
+        count,
+        v0,
+        spacing,
+        seed,
+        ..
+    } = *params;
+    let g = gravity(params);
+    let x_max = v0 * v0 / g;
+
+    // Evenly spaced fractions strictly between 0 and 1, each giving a shot on both sides
+    let per_side = count / 2;
+    let fractions = (1..=per_side).map(|i| i as f64 / (per_side + 1) as f64);
+    let both_sides = |th: f64| [th, PI - th];
+
+    match spacing {
+        ParabolaSpacing::EqualXIntersect => linspace(-x_max * 0.95, x_max * 0.95, count / 2)
+            .filter(|&x| x != 0.)
+            .flat_map(|x| {
+                // Towards the left, the low shot is turned over instead of shot downwards
+                let th = (g * x / (v0 * v0)).asin() / 2.;
+                [th.rem_euclid(PI), th + FRAC_PI_2]
+            })
+            .collect(),
+        // In the middle of equal sectors, missing the vertical and the ground
+        ParabolaSpacing::EqualAngle => (0..count)
+            .map(|i| (i as f64 + 0.5) * PI / count as f64)
+            .collect(),
+        // The flight lasts 2 v0 sin(θ) / g
+        ParabolaSpacing::EqualTimeOfFlight => {
+            fractions.flat_map(|f: f64| both_sides(f.asin())).collect()
+        }
+        // The apex is at v0² sin²(θ) / 2g
+        ParabolaSpacing::EqualHeight => fractions
+            .flat_map(|f: f64| both_sides(f.sqrt().asin()))
+            .collect(),
+        // The shot touches the safety parabola at x = v0² / (g tan(θ)), the tangency points
+        // going down to twice the range on flat ground
+        ParabolaSpacing::EqualTangency => fractions
+            .flat_map(|f| both_sides((1. / (2. * f)).atan()))
+            .collect(),
+        ParabolaSpacing::Random => random(seed as u64).take(count).map(|f| f * PI).collect(),
+    }
+}
+
+///
+/// The safety curve to the right as `(r, z)`, from where it meets the ground to its apex. With
+/// air resistance it goes through the touches of the shots stopped on the ground, the farthest
+/// one being extended down to the ground.
+///
+fn safety_profile(params: &Params) -> Vec<(f64, f64)> {
+    let (a, h) = (apex(params, params.v0), params.launch_height);
+    if params.air_resistance == AirResistance::None {
+        let radius = 2. * (a * (h + a)).sqrt();
+        return linspace(radius, 0., RESOLUTION)
+            .map(|r| (r, h + a - r * r / (4. * a)))
+            .collect();
+    }
+
+    let mut profile: Vec<_> = envelope_points(params, vec2d(f64::INFINITY, 0.))
+        .into_iter()
+        .map(|touch| (touch.point.x(), touch.point.y()))
+        .collect();
+    if let [(r0, z0), (r1, z1), ..] = profile[..] {
+        if z0 > 0. && z1 > z0 {
+            profile.insert(0, (r0 + (r0 - r1) * z0 / (z1 - z0), 0.));
+        }
+    }
+    profile
+}
+
+/// Radius where the safety paraboloid meets the ground, and its height
+fn paraboloid_extent(profile: &[(f64, f64)]) -> (f64, f64) {
+    let radius = profile.iter().map(|&(r, _)| r).fold(0., f64::max);
+    let height = profile.iter().map(|&(_, z)| z).fold(0., f64::max);
+    (radius, height)
+}
+
+/// Camera turning around the middle of the safety paraboloid of the given extent
+fn camera(params: &Params, (radius, height): (f64, f64)) -> Camera {
+    Camera::new(params.yaw, params.pitch)
+        .with_projection(params.projection, 3. * radius.max(height))
+        .looking_at((0., 0., height / 2.))
+}
+
+#[derive(Default)]
+struct SafetyParabola {
+    /// Positions of the projectiles every `TIME_STEP`, filled each time the cache is redrawn
+    flights: RefCell<Vec<Vec<Vector2D>>>,
+    /// Where and when the projectiles towards the right touch the envelope, filled with them
+    touches: RefCell<Vec<Touch>>,
+}
+
+impl SafetyParabola {
+    /// The shots in every direction around the cannon, and the safety paraboloid enveloping them
+    fn draw_3d(&self, params: &Params, frame: &mut Frame, plotter: &Plotter) {
+        // Nothing flies in 3D
+        self.flights.borrow_mut().clear();
+        self.touches.borrow_mut().clear();
+
+        let profile = safety_profile(params);
+        let (radius, height) = paraboloid_extent(&profile);
+        let camera = camera(params, (radius, height));
+        // The shots stop on the ground instead of the edges of the view
+        let ground = vec2d(f64::INFINITY, 0.);
+
+        frame.stroke(
+            &camera.axes(plotter, 0.6 * radius.max(height)),
+            Stroke::default().with_width(2.0).with_color(Color::BLACK),
+        );
+        frame.stroke(
+            &camera.horizontal_circle(plotter, 0., radius),
+            Stroke::default()
+                .with_width(2.0)
+                .with_color(Color::from_rgb(0.55, 0.35, 0.15)),
+        );
+
+        // Each vertical plane through the cannon holds the shots of the 2D view
+        let around = |(r, z): (f64, f64), azimuth: f64| (r * azimuth.cos(), r * azimuth.sin(), z);
+        let azimuths: Vec<_> = (0..params.azimuths)
+            .map(|i| TAU * i as f64 / params.azimuths as f64)
+            .collect();
+        let thetas = thetas(params)
+            .into_iter()
+            .filter(|&th| th > 0. && th < FRAC_PI_2);
+        for theta in thetas {
+            let shot = trajectory(params, theta, ground);
+            for &azimuth in &azimuths {
                 frame.stroke(
-                    &parabola,
-                    Stroke::default().with_width(3.0).with_color(Color::BLACK),
-                )
+                    &camera.path(
+                        plotter,
+                        shot.iter().map(|p| around((p.x(), p.y()), azimuth)),
+                    ),
+                    Stroke::default().with_width(1.5).with_color(Color::BLACK),
+                );
             }
+        }
+
+        // The safety curve turned around the vertical, from the ground to the apex
+        let wireframe = Stroke::default()
+            .with_width(1.5)
+            .with_color(Color::from_rgba(1., 0., 0., 0.7));
+        for i in 0..MERIDIANS {
+            let azimuth = TAU * i as f64 / MERIDIANS as f64;
+            frame.stroke(
+                &camera.path(plotter, profile.iter().map(|&p| around(p, azimuth))),
+                wireframe.clone(),
+            );
+        }
+        for &(r, z) in profile.iter().step_by((profile.len() / RINGS).max(1)) {
+            frame.stroke(&camera.horizontal_circle(plotter, z, r), wireframe.clone());
+        }
+    }
+}
+
+pub fn main() -> iced::Result {
+    plotter::run::<SafetyParabola>()
+}
+
+impl Visualization for SafetyParabola {
+    type Params = Params;
 
-            let safety_parabola =
-                plotter.parabola(-EARTH_G / (2. * v0 * v0), 0., v0 * v0 / (2. * EARTH_G));
+    const TITLE: &'static str = "Safety Parabola";
+    const ANIMATED: bool = true;
 
+    ///
+    /// The view fits the safety curve, from its apex to where it reaches the ground. It follows
+    /// v0, the gravity and the air resistance, but is kept while the apex handle is dragged so
+    /// that the handle stays under the cursor. In 3D it fits the safety paraboloid turned in
+    /// any direction.
+    ///
+    fn plotter(&self, params: &Params, size: Size) -> Plotter {
+        let (range, top) = paraboloid_extent(&safety_profile(params));
+        let (width, height) = (f64::from(size.width), f64::from(size.height));
+        let scale = if params.view_3d {
+            width.min(height) / (2. * range.max(top))
+        } else {
+            (width / (2. * range)).min(height / (2. * top))
+        } / VIEW_MARGIN;
+        Plotter::new(RESOLUTION, size.width as _, size.height as _, scale)
+    }
+
+    /// The apex of the safety curve can be dragged along the y axis to change v0
+    fn handles(&self, params: &Params) -> Handles<Param> {
+        if params.view_3d {
+            return Handles::new();
+        }
+        let apex = |v0| params.launch_height + apex_with_drag(params, v0);
+
+        Handles::new().with(
+            Handle::new(Param::V0, (0., apex(params.v0)))
+                .constrained(Constraint::Segment(
+                    vec2d(0., apex(*V0_RANGE.start())),
+                    vec2d(0., apex(*V0_RANGE.end())),
+                ))
+                .with_color(Color::from_rgb(1., 0., 0.))
+                .keeping_view(),
+        )
+    }
+
+    /// A click places the target, when there is one
+    fn click(&self, params: &Params, position: Vector2D) -> Vec<(Param, Value)> {
+        if !params.target || params.view_3d {
+            return Vec::new();
+        }
+        vec![
+            (Param::TargetX, Value::Float(position.x())),
+            (Param::TargetY, Value::Float(position.y())),
+        ]
+    }
+
+    /// Dragging the 3D view turns it
+    fn drag_canvas(
+        &self,
+        params: &Params,
+        plotter: &Plotter,
+        from: Vector2D,
+        to: Vector2D,
+    ) -> Vec<(Param, Value)> {
+        if !params.view_3d {
+            return Vec::new();
+        }
+        let (yaw, pitch) =
+            camera(params, paraboloid_extent(&safety_profile(params))).dragged(plotter, from, to);
+        vec![
+            (Param::Yaw, Value::Float(yaw)),
+            (Param::Pitch, Value::Float(pitch)),
+        ]
+    }
+
+    fn drag(&self, params: &Params, _key: Key<Self>, position: Vector2D) -> Option<Value> {
+        let height = position.y() - params.launch_height;
+        let v0 = match params.air_resistance {
+            AirResistance::None => (2. * gravity(params) * height).sqrt(),
+            _ => speed_for_apex(params, height),
+        };
+        Some(Value::Float(v0))
+    }
+
+    ///
+    /// The projectiles are all launched together, and again once the last one has left the view.
+    /// The envelope is drawn up to where the wavefront of projectiles has touched it.
+    ///
+    fn animate(&self, params: &Params, frame: &mut Frame, plotter: &Plotter, time: f64) {
+        if !params.projectiles || params.view_3d {
+            return;
+        }
+
+        let flights = self.flights.borrow();
+        let duration = flights.iter().map(Vec::len).max().unwrap_or(0) as f64 * TIME_STEP;
+        if duration == 0. {
+            return;
+        }
+        let time = animation::cycle(time, duration) * duration;
+
+        let reached: Vec<_> = self
+            .touches
+            .borrow()
+            .iter()
+            .filter(|touch| touch.time <= time)
+            .map(|touch| touch.point)
+            .collect();
+        let mirrored: Vec<_> = reached.iter().map(|p| vec2d(-p.x(), p.y())).collect();
+        for side in [reached, mirrored] {
             frame.stroke(
-                &safety_parabola,
+                &plotter.path(side),
                 Stroke::default()
                     .with_width(3.0)
                     .with_color(Color::from_rgb(1., 0., 0.)),
+            );
+        }
+
+        let step = (time / TIME_STEP) as usize;
+        for position in flights.iter().filter_map(|flight| flight.get(step)) {
+            frame.fill(
+                &plotter.circle(position.x(), position.y(), 4.5 / plotter.scale()),
+                Color::BLACK,
+            );
+        }
+    }
+
+    fn draw(&self, params: &Params, frame: &mut Frame, plotter: &Plotter) {
+        if params.view_3d {
+            return self.draw_3d(params, frame, plotter);
+        }
+
+        let Params {
+            v0,
+            computed_envelope,
+            air_resistance,
+            launch_height,
+            slope,
+            terrain,
+            target,
+            target_x,
+            target_y,
+            projectiles,
+            apexes,
+            foci,
+            ..
+        } = *params;
+        let g = gravity(params);
+        let bounds = vec2d(plotter.width() / 2., plotter.height() / 2.);
+        // Sizes of the markers are given in pixels
+        let pixel = 1. / plotter.scale();
+
+        let axis = plotter.axis();
+        frame.stroke(
+            &axis,
+            Stroke::default().with_width(2.0).with_color(Color::BLACK),
+        );
+        draw_graduations(params.units, frame, plotter, bounds);
+
+        let make_parabola = |th: f64| {
+            let a = -g / (2. * v0 * v0 * th.cos() * th.cos());
+            let b = th.tan();
+
+            plotter.parabola(a, b, launch_height)
+        };
+
+        let x_max = v0 * v0 / g;
+        let thetas = thetas(params);
+
+        // The envelope is integrated once per draw, with the shots going down to the bottom of
+        // the view but past its sides, for the range on flat ground
+        let touches = match air_resistance == AirResistance::None && !projectiles {
+            true => Vec::new(),
+            false => envelope_points(params, vec2d(f64::INFINITY, bounds.y())),
+        };
+
+        // In flight, the trajectories fade behind the projectiles and the envelope is drawn as
+        // they reach it
+        let flights = match projectiles {
+            true => thetas
+                .iter()
+                .map(|&th| trajectory(params, th, bounds))
+                .collect(),
+            false => Vec::new(),
+        };
+        *self.flights.borrow_mut() = flights;
+        *self.touches.borrow_mut() = match projectiles {
+            true => touches.clone(),
+            false => Vec::new(),
+        };
+
+        let trajectory_color = match projectiles {
+            true => Color::from_rgba(0., 0., 0., 0.2),
+            false => Color::BLACK,
+        };
+        let trajectories = thetas.iter().map(|&th| match air_resistance {
+            AirResistance::None => make_parabola(th),
+            _ => plotter.path(trajectory(params, th, bounds)),
+        });
+        for trajectory in trajectories {
+            frame.stroke(
+                &trajectory,
+                Stroke::default()
+                    .with_width(3.0)
+                    .with_color(trajectory_color),
             )
-        })]
+        }
+
+        let red = Color::from_rgb(1., 0., 0.);
+        let safety_parabola =
+            plotter.parabola(-g / (2. * v0 * v0), 0., launch_height + apex(params, v0));
+
+        if air_resistance == AirResistance::None {
+            if !projectiles {
+                frame.stroke(
+                    &safety_parabola,
+                    Stroke::default().with_width(3.0).with_color(red),
+                );
+            }
+        } else {
+            if !projectiles {
+                frame.stroke(
+                    &plotter.path(safety_curve(&touches)),
+                    Stroke::default().with_width(3.0).with_color(red),
+                );
+            }
+
+            // The safety parabola without air resistance, for comparison
+            frame.stroke(
+                &safety_parabola,
+                Stroke {
+                    line_dash: LineDash {
+                        segments: &[4., 6.],
+                        offset: 0,
+                    },
+                    ..Stroke::default()
+                        .with_width(2.0)
+                        .with_color(Color { a: 0.5, ..red })
+                },
+            );
+        }
+
+        if computed_envelope {
+            let computed = if air_resistance == AirResistance::None {
+                let trajectory = |x: Dual, y: Dual, th: Dual| {
+                    y - launch_height - th.tan() * x + g * x * x / (2. * v0 * v0 * th.cos().powi(2))
+                };
+
+                // The shot at 45° touches the envelope close to the height of the launch, we
+                // follow it from there towards both ends
+                let descending = envelope(
+                    trajectory,
+                    linspace(FRAC_PI_4, 0.05, RESOLUTION),
+                    vec2d(x_max, launch_height),
+                );
+                let ascending = envelope(
+                    trajectory,
+                    linspace(FRAC_PI_4, PI - 0.05, 2 * RESOLUTION),
+                    vec2d(x_max, launch_height),
+                );
+                plotter.path(descending.into_iter().rev().chain(ascending))
+            } else {
+                // The shots have no closed form with air resistance, the envelope is found from
+                // the integrated ones
+                plotter.path(safety_curve(&touches))
+            };
+            frame.stroke(
+                &computed,
+                Stroke {
+                    line_dash: LineDash {
+                        segments: &[8., 8.],
+                        offset: 0,
+                    },
+                    ..Stroke::default()
+                        .with_width(3.0)
+                        .with_color(Color::from_rgb(0., 0., 1.))
+                },
+            );
+        }
+
+        // Lines of the labels in the corner
+        let mut lines = 0;
+
+        // On flat ground at the height of the foot of the cannon
+        let flat = Params {
+            slope: 0.,
+            ..params.clone()
+        };
+        let range = match best_landing(&flat, &touches) {
+            Some((landing, _)) => params.units.show(landing.x()),
+            None => "out of the view".to_owned(),
+        };
+        let extent = format!(
+            "Max height: {}, max range: {}, g = {:.2}{}",
+            params
+                .units
+                .show(launch_height + apex_with_drag(params, v0)),
+            range,
+            g * params.units.length(),
+            params.units.acceleration_unit(),
+        );
+        frame.fill_text(label(plotter, bounds, lines, extent, Color::BLACK));
+        lines += 1;
+
+        if params.angle_readout {
+            let mut angles: Vec<_> = thetas.iter().map(|th| th.to_degrees()).collect();
+            angles.sort_by(f64::total_cmp);
+
+            let header = format!("Angles of the shots, {}:", params.spacing);
+            frame.fill_text(label(plotter, bounds, lines, header, Color::BLACK));
+            lines += 1;
+            for row in angles.chunks(ANGLES_PER_LINE) {
+                let row: Vec<_> = row.iter().map(|a| format!("{a:.1}°")).collect();
+                frame.fill_text(label(plotter, bounds, lines, row.join(", "), Color::BLACK));
+                lines += 1;
+            }
+        }
+
+        // Without air resistance, with A = v0²/2g, the trajectory at θ is the parabola
+        // y = h + A sin²(θ) - (x - A sin(2θ))² / 4A cos²(θ)
+        let a = apex(params, v0);
+        let dashed = |color| Stroke {
+            line_dash: LineDash {
+                segments: &[6., 6.],
+                offset: 0,
+            },
+            ..Stroke::default().with_width(2.0).with_color(color)
+        };
+
+        if apexes {
+            let purple = Color::from_rgb(0.5, 0., 0.7);
+            for &th in &thetas {
+                let apex = match air_resistance {
+                    AirResistance::None => Some(vec2d(
+                        a * (2. * th).sin(),
+                        launch_height + a * th.sin().powi(2),
+                    )),
+                    _ => shot_apex(params, th, bounds),
+                };
+                if let Some(apex) = apex {
+                    frame.fill(&plotter.circle(apex.x(), apex.y(), 4.5 * pixel), purple);
+                }
+            }
+
+            let content = match air_resistance {
+                AirResistance::None => {
+                    // x = A sin(2θ) and y - h - A/2 = -A cos(2θ) / 2
+                    frame.stroke(
+                        &plotter.ellipse(0., launch_height + a / 2., a, a / 2.),
+                        dashed(purple),
+                    );
+                    "Apexes: on an ellipse of semi-axes v0²/2g and v0²/4g".to_owned()
+                }
+                _ => "Apexes: no longer on an ellipse with air resistance".to_owned(),
+            };
+            frame.fill_text(label(plotter, bounds, lines, content, purple));
+            lines += 1;
+        }
+
+        if foci {
+            let teal = Color::from_rgb(0., 0.5, 0.5);
+            let content = match air_resistance {
+                AirResistance::None => {
+                    // The focus is A cos²(θ) below the apex, and the directrix as much above
+                    for &th in &thetas {
+                        let focus = vec2d(a * (2. * th).sin(), launch_height - a * (2. * th).cos());
+                        frame.fill(&plotter.circle(focus.x(), focus.y(), 4.5 * pixel), teal);
+                    }
+                    frame.stroke(&plotter.circle(0., launch_height, a), dashed(teal));
+                    frame.stroke(
+                        &plotter.path([
+                            vec2d(-bounds.x(), launch_height + a),
+                            vec2d(bounds.x(), launch_height + a),
+                        ]),
+                        dashed(teal),
+                    );
+                    format!(
+                        "Foci: on a circle of radius v0²/2g around the cannon, \
+                         common directrix at y = {}",
+                        params.units.show(launch_height + a)
+                    )
+                }
+                _ => {
+                    "Foci: the trajectories are no longer parabolas with air resistance".to_owned()
+                }
+            };
+            frame.fill_text(label(plotter, bounds, lines, content, teal));
+            lines += 1;
+        }
+
+        if terrain {
+            let brown = Color::from_rgb(0.55, 0.35, 0.15);
+            frame.stroke(
+                &plotter.path([
+                    vec2d(-bounds.x(), -bounds.x() * slope.tan()),
+                    vec2d(bounds.x(), bounds.x() * slope.tan()),
+                ]),
+                Stroke::default().with_width(3.0).with_color(brown),
+            );
+
+            if let Some((landing, theta)) = best_landing(params, &touches) {
+                let green = Color::from_rgb(0., 0.6, 0.);
+                let best = match air_resistance {
+                    AirResistance::None => make_parabola(theta),
+                    _ => plotter.path(trajectory(params, theta, bounds)),
+                };
+                frame.stroke(&best, Stroke::default().with_width(4.0).with_color(green));
+                frame.stroke(
+                    &plotter.angle_marker(
+                        (0., launch_height),
+                        (1., 0.),
+                        (theta.cos(), theta.sin()),
+                        37.5 * pixel,
+                    ),
+                    Stroke::default().with_width(2.0).with_color(green),
+                );
+                frame.fill(&plotter.circle(landing.x(), landing.y(), 6. * pixel), green);
+
+                // The terrain goes through the foot of the cannon
+                let range = format!(
+                    "Range along the slope: {}, best angle: {:.1}°",
+                    params.units.show(landing.norm()),
+                    theta.to_degrees()
+                );
+                frame.fill_text(label(plotter, bounds, lines, range, green));
+                lines += 1;
+            }
+        }
+
+        if target {
+            let (reach, angles) = aim(params, bounds);
+            let orange = Color::from_rgb(1., 0.5, 0.);
+
+            for &theta in &angles {
+                let shot = match air_resistance {
+                    AirResistance::None => make_parabola(theta),
+                    _ => plotter.path(trajectory(params, theta, bounds)),
+                };
+                frame.stroke(&shot, Stroke::default().with_width(4.0).with_color(orange));
+            }
+            frame.stroke(
+                &plotter.circle(target_x, target_y, 7.5 * pixel),
+                Stroke::default().with_width(3.0).with_color(orange),
+            );
+
+            let curve = match air_resistance {
+                AirResistance::None => "safety parabola",
+                _ => "safety curve",
+            };
+            let angles: Vec<_> = angles
+                .iter()
+                .map(|theta| format!("{:.1}°", theta.to_degrees()))
+                .collect();
+            let reached = match angles.len() {
+                0 => "no shot reaches it in the view".to_owned(),
+                _ => format!("reached at {}", angles.join(" and ")),
+            };
+            let content = match reach {
+                Reach::Inside => format!("Inside the {curve}: {reached}"),
+                Reach::On => format!("On the {curve}: {reached} only"),
+                Reach::Outside => format!("Outside the {curve}: out of reach"),
+            };
+            frame.fill_text(label(plotter, bounds, lines, content, orange));
+        }
+
+        if launch_height > 0. {
+            frame.fill(&plotter.circle(0., launch_height, 6. * pixel), Color::BLACK);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Far enough for the shots to land, they end 50 m below the launch
+    fn bounds() -> Vector2D {
+        vec2d(f64::INFINITY, 50.)
+    }
+
+    fn with_drag(air_resistance: AirResistance, drag_coefficient: f64) -> Params {
+        Params {
+            air_resistance,
+            drag_coefficient,
+            ..Params::default()
+        }
+    }
+
+    /// Highest point of the integrated vertical shot
+    fn vertical_apex(params: &Params) -> f64 {
+        shot(params, FRAC_PI_2, bounds())
+            .iter()
+            .map(|&[_, y, _, _]| y)
+            .fold(f64::NEG_INFINITY, f64::max)
+    }
+
+    #[test]
+    fn parabola_without_drag() {
+        for params in [
+            Params::default(),
+            with_drag(AirResistance::Linear, 1e-7),
+            with_drag(AirResistance::Quadratic, 1e-7),
+        ] {
+            let (v0, g) = (params.v0, gravity(&params));
+            for theta in [0.3, FRAC_PI_4, 1.2] {
+                let parabola =
+                    |x: f64| theta.tan() * x - g * x * x / (2. * (v0 * theta.cos()).powi(2));
+                let flight = shot(&params, theta, bounds());
+                for [x, y, _, _] in flight.into_iter().take_while(|&[_, y, ..]| y > -10.) {
+                    assert!((y - parabola(x)).abs() < 1e-4, "{x} {y}");
+                }
+            }
+
+            let apex = v0 * v0 / (2. * g);
+            assert!((apex_with_drag(&params, v0) - apex).abs() < 1e-4);
+            assert!((vertical_apex(&params) - apex).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn apex_with_drag_closed_forms() {
+        for air_resistance in [AirResistance::Linear, AirResistance::Quadratic] {
+            for k in [0.05, 0.3, 1.] {
+                let params = with_drag(air_resistance, k);
+                let apex = apex_with_drag(&params, params.v0);
+                assert!(apex < super::apex(&params, params.v0));
+                assert!((vertical_apex(&params) - apex).abs() < 1e-3, "{k}");
+            }
+        }
+    }
+
+    #[test]
+    fn drag_shortens_the_range() {
+        let flat = |params: &Params| {
+            let touches = envelope_points(params, bounds());
+            best_landing(params, &touches).unwrap().0.x()
+        };
+        let range = flat(&Params::default());
+        assert!((range - DEFAULT_V0 * DEFAULT_V0 / EARTH_G).abs() < 1e-9);
+
+        let mut previous = range;
+        for k in [0.01, 0.1, 0.5] {
+            let linear = flat(&with_drag(AirResistance::Linear, k));
+            assert!(linear < previous, "{k}");
+            previous = linear;
+            assert!(flat(&with_drag(AirResistance::Quadratic, k)) < range);
+        }
+    }
+
+    fn landing(params: &Params) -> (Vector2D, f64) {
+        let touches = match params.air_resistance {
+            AirResistance::None => Vec::new(),
+            _ => envelope_points(params, bounds()),
+        };
+        best_landing(params, &touches).unwrap()
+    }
+
+    #[test]
+    fn range_on_an_incline() {
+        let (v0, g) = (DEFAULT_V0, EARTH_G);
+        for degrees in [-30., -10., 0., 20., 45.] {
+            let slope: f64 = degrees * PI / 180.;
+            let params = Params {
+                slope,
+                ..Params::default()
+            };
+            let (point, theta) = landing(&params);
+
+            // Along the terrain, the best shot bisects the slope and the vertical
+            let distance = point.norm();
+            assert!((distance - v0 * v0 / (g * (1. + slope.sin()))).abs() < 1e-9);
+            assert!((point.y() - point.x() * slope.tan()).abs() < 1e-9);
+            assert!((theta - (FRAC_PI_4 + slope / 2.)).abs() < 1e-9);
+
+            // The shot lands there
+            let height = height_at(&params, theta, point.x(), bounds()).unwrap();
+            assert!((height - point.y()).abs() < 1e-3, "{degrees}");
+        }
+    }
+
+    #[test]
+    fn range_from_a_height() {
+        let (v0, g) = (DEFAULT_V0, EARTH_G);
+        for launch_height in [0., 1., 5.] {
+            let params = Params {
+                launch_height,
+                ..Params::default()
+            };
+            let (point, theta) = landing(&params);
+            let range = v0 / g * (v0 * v0 + 2. * g * launch_height).sqrt();
+            assert!((point.x() - range).abs() < 1e-9);
+            assert_eq!(point.y(), 0.);
+            assert!((theta.tan() - v0 / (v0 * v0 + 2. * g * launch_height).sqrt()).abs() < 1e-9);
+
+            // Air resistance lands shorter, still from the height
+            let (dragged, _) = landing(&Params {
+                air_resistance: AirResistance::Linear,
+                ..params.clone()
+            });
+            assert!(dragged.x() < point.x());
+            assert!(dragged.y().abs() < 1e-9);
+        }
+    }
+
+    /// The angles of the shots through the target, checking that they go through it
+    fn aim_at(params: &Params, x: f64, y: f64, tolerance: f64) -> (Reach, Vec<f64>) {
+        let params = Params {
+            target_x: x,
+            target_y: y,
+            ..params.clone()
+        };
+        let (reach, angles) = aim(&params, bounds());
+        for &theta in &angles {
+            let height = height_at(&params, theta, x, bounds()).unwrap();
+            assert!((height - y).abs() < tolerance, "{theta}: {height} != {y}");
+        }
+
+        // Towards the left, the angles are mirrored
+        let (mirrored_reach, mirrored) = aim(
+            &Params {
+                target_x: -x,
+                ..params
+            },
+            bounds(),
+        );
+        assert_eq!(mirrored_reach, reach);
+        for (theta, mirrored) in angles.iter().zip(mirrored) {
+            assert!((mirrored - (PI - theta)).abs() < 1e-12);
+        }
+
+        (reach, angles)
+    }
+
+    #[test]
+    fn aim_without_drag() {
+        let params = Params::default();
+        let a = apex(&params, params.v0);
+        let x = 3.;
+        let safety = a - x * x / (4. * a);
+
+        let (reach, angles) = aim_at(&params, x, safety + 1., 1e-3);
+        assert_eq!((reach, angles.len()), (Reach::Outside, 0));
+        let (reach, angles) = aim_at(&params, x, safety, 1e-3);
+        assert_eq!((reach, angles.len()), (Reach::On, 1));
+        let (reach, angles) = aim_at(&params, x, 1., 1e-3);
+        assert_eq!((reach, angles.len()), (Reach::Inside, 2));
+        assert!(angles[0] < angles[1]);
+
+        // Above the cannon, only straight up
+        let above = Params {
+            target_x: 0.,
+            target_y: a - 1.,
+            ..params
+        };
+        assert_eq!(aim(&above, bounds()), (Reach::Inside, vec![FRAC_PI_2]));
+    }
+
+    #[test]
+    fn aim_with_drag() {
+        let params = with_drag(AirResistance::Linear, 0.1);
+        let touches = envelope_points(&params, bounds());
+        let on = touches[touches.len() / 2].point;
+
+        let (reach, angles) = aim_at(&params, on.x(), on.y() + 1., ON_CURVE);
+        assert_eq!((reach, angles.len()), (Reach::Outside, 0));
+        let (reach, angles) = aim_at(&params, on.x(), on.y(), ON_CURVE);
+        assert_eq!((reach, angles.len()), (Reach::On, 1));
+        let (reach, angles) = aim_at(&params, on.x(), on.y() - 1., 1e-3);
+        assert_eq!((reach, angles.len()), (Reach::Inside, 2));
+    }
+
+    fn spaced(spacing: ParabolaSpacing, count: usize, seed: usize) -> Vec<f64> {
+        thetas(&Params {
+            spacing,
+            count,
+            seed,
+            ..Params::default()
+        })
+    }
+
+    #[test]
+    fn thetas_count_and_bounds() {
+        for &spacing in ParabolaSpacing::ALL {
+            for count in [4, 12, 40] {
+                let thetas = spaced(spacing, count, 1);
+                assert_eq!(thetas.len(), count, "{spacing}");
+                assert!(thetas.iter().all(|th| (0. ..PI).contains(th)), "{spacing}");
+            }
+        }
+        // The landings stay symmetric around the cannon, which is left out, so an odd number of
+        // them per side gives one more pair
+        assert_eq!(spaced(ParabolaSpacing::EqualXIntersect, 10, 1).len(), 12);
+    }
+
+    #[test]
+    fn random_thetas_follow_the_seed() {
+        let random = |seed| spaced(ParabolaSpacing::Random, 20, seed);
+        assert_eq!(random(7), random(7));
+        assert_ne!(random(7), random(8));
+    }
+
+    #[test]
+    fn tangency_points_are_evenly_spaced() {
+        let params = Params {
+            spacing: ParabolaSpacing::EqualTangency,
+            ..Params::default()
+        };
+        let a = apex(&params, params.v0);
+
+        // Where the slope of the shot is the one of the safety parabola
+        let tangency = |theta| {
+            let slope = |[x, _, vx, vy]: [f64; 4]| vy / vx + x / (2. * a);
+            let shot = shot(&params, theta, bounds());
+            let j = (1..shot.len()).find(|&j| slope(shot[j]) <= 0.).unwrap();
+            let (s0, s1) = (slope(shot[j - 1]), slope(shot[j]));
+            let (x0, x1) = (shot[j - 1][0], shot[j][0]);
+            x0 + (x1 - x0) * s0 / (s0 - s1)
+        };
+        let mut xs: Vec<f64> = thetas(&params)
+            .into_iter()
+            .filter(|&th| th < FRAC_PI_2)
+            .map(tangency)
+            .collect();
+        xs.sort_by(f64::total_cmp);
+
+        assert_eq!(xs.len(), params.count / 2);
+        let step = xs[0];
+        for (i, x) in xs.iter().enumerate() {
+            assert!((x - step * (i + 1) as f64).abs() < 1e-2, "{x}");
+        }
     }
 }
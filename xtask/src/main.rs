@@ -1,7 +1,7 @@
 use std::{
     fs::{File, OpenOptions},
     io::{BufRead, BufReader},
-    path::Path,
+    path::{Path, PathBuf},
     process::Command,
 };
 
@@ -22,6 +22,32 @@ enum Commands {
         #[clap(short, long, default_value = "8300")]
         port: u16,
     },
+    /// Record a post to an animated .gif or .png, or to a directory of PNG frames
+    Record {
+        post: String,
+        output: PathBuf,
+        /// Recording options given to the post, like `-- --sweep v0 --duration 3`
+        #[clap(last = true)]
+        args: Vec<String>,
+    },
+}
+
+fn record(post: &str, output: &Path, args: &[String], posts: &[&str]) -> color_eyre::Result<()> {
+    if !posts.contains(&post) {
+        color_eyre::eyre::bail!("Unknown post {post}, expected one of: {}", posts.join(", "))
+    }
+
+    let status = Command::new(env!("CARGO"))
+        .args(["run", "--release", "--package", post, "--", "--record"])
+        .arg(output)
+        .args(args)
+        .spawn()?
+        .wait()?;
+    if !status.success() {
+        color_eyre::eyre::bail!("Recording failed: {status}")
+    }
+
+    Ok(())
 }
 
 fn build(
@@ -86,7 +112,7 @@ fn build(
 
         let mut command = Command::new("trunk");
         command
-            .args(&["build", "--dist"])
+            .args(["build", "--dist"])
             .args(&[target.join(post), index])
             .arg("--public-url")
             .arg(format!("/{post}"))
@@ -171,6 +197,7 @@ async fn main() -> color_eyre::Result<()> {
 
     match args {
         Commands::Build { release } => build(release, workspace, &posts, &template),
+        Commands::Record { post, output, args } => record(&post, &output, &args, &posts),
         Commands::Serve { release, port } => {
             build(release, workspace, &posts, &template)?;
             let addr = std::net::SocketAddr::from(([127, 0, 0, 1], port));